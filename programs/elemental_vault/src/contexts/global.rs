@@ -1,6 +1,8 @@
 use anchor_lang::prelude::*;
 
-use crate::state::Global;
+use crate::error::ErrorCode;
+use crate::program::ElementalVault;
+use crate::state::{Credential, Global, ManualPrice};
 
 #[derive(Accounts)]
 pub struct InitGlobal<'info> {
@@ -16,5 +18,91 @@ pub struct InitGlobal<'info> {
         space = 8 + std::mem::size_of::<Global>(),
     )]
     pub global: Account<'info, Global>,
+    // This program, to find its program data
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()) @ ErrorCode::Unauthorized)]
+    pub program: Program<'info, ElementalVault>,
+    // Program data, the upgrade authority becomes the admin
+    #[account(constraint = program_data.upgrade_authority_address == Some(initializer.key()) @ ErrorCode::Unauthorized)]
+    pub program_data: Account<'info, ProgramData>,
     pub system_program: Program<'info, System>,
 }
+
+#[derive(Accounts)]
+pub struct SetManualPrice<'info> {
+    // Global admin
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    // Global State
+    #[account(
+        seeds = [Global::seed()],
+        bump,
+        constraint = global.admin == admin.key() @ ErrorCode::Unauthorized
+    )]
+    pub global: Account<'info, Global>,
+    // Manual price PDA for the collateral mint
+    #[account(
+        init_if_needed,
+        payer = admin,
+        seeds = [ManualPrice::seed(), collateral_mint.key().as_ref()],
+        bump,
+        space = 8 + std::mem::size_of::<ManualPrice>(),
+    )]
+    pub manual_price: Account<'info, ManualPrice>,
+    /// CHECK: Only used as a seed for the manual price
+    pub collateral_mint: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateAttestor<'info> {
+    // Global admin
+    pub admin: Signer<'info>,
+    // Global State
+    #[account(
+        mut,
+        seeds = [Global::seed()],
+        bump,
+        constraint = global.admin == admin.key() @ ErrorCode::Unauthorized
+    )]
+    pub global: Account<'info, Global>,
+}
+
+#[derive(Accounts)]
+#[instruction(wallet: Pubkey)]
+pub struct IssueCredential<'info> {
+    // Attestor registered in Global
+    #[account(mut)]
+    pub attestor: Signer<'info>,
+    // Global State
+    #[account(
+        seeds = [Global::seed()],
+        bump,
+        constraint = global.is_attestor(&attestor.key()) @ ErrorCode::Unauthorized
+    )]
+    pub global: Account<'info, Global>,
+    // Credential PDA for the wallet
+    #[account(
+        init_if_needed,
+        payer = attestor,
+        seeds = [Credential::seed(), attestor.key().as_ref(), wallet.as_ref()],
+        bump,
+        space = 8 + std::mem::size_of::<Credential>(),
+    )]
+    pub credential: Account<'info, Credential>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeCredential<'info> {
+    // Issuer of the credential
+    #[account(mut)]
+    pub attestor: Signer<'info>,
+    // Credential PDA for the wallet
+    #[account(
+        mut,
+        seeds = [Credential::seed(), attestor.key().as_ref(), credential.wallet.as_ref()],
+        bump,
+        close = attestor
+    )]
+    pub credential: Account<'info, Credential>,
+}
@@ -0,0 +1,97 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+use crate::state::{Proposal, User, Vault, VaultMode, Vote};
+
+#[derive(Accounts)]
+#[instruction(vault_count: u64)]
+pub struct CreateProposal<'info> {
+    // vault authority
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    // Vault that holds state
+    #[account(
+        mut, seeds = [Vault::seed(), &vault_count.to_le_bytes()], bump,
+        constraint = vault.authority == authority.key() @ ErrorCode::Unauthorized,
+        constraint = vault.mode == VaultMode::FixedTerm @ ErrorCode::InvalidVaultMode
+    )]
+    pub vault: Account<'info, Vault>,
+    // Proposal PDA
+    #[account(
+        init,
+        payer = authority,
+        seeds = [
+            Proposal::seed(),
+            &vault_count.to_le_bytes(),
+            &vault.proposal_count.to_le_bytes()
+        ],
+        bump,
+        space = 8 + std::mem::size_of::<Proposal>(),
+    )]
+    pub proposal: Account<'info, Proposal>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(vault_count: u64, proposal_id: u64)]
+pub struct CastVote<'info> {
+    // User's wallet
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    // Vault that holds state
+    #[account(
+        seeds = [Vault::seed(), &vault_count.to_le_bytes()],
+        bump,
+    )]
+    pub vault: Account<'info, Vault>,
+    // User PDA
+    #[account(
+        mut,
+        seeds = [User::seed(), &vault_count.to_le_bytes(), owner.key().as_ref()],
+        bump,
+    )]
+    pub user: Account<'info, User>,
+    // Proposal PDA
+    #[account(
+        mut,
+        seeds = [Proposal::seed(), &vault_count.to_le_bytes(), &proposal_id.to_le_bytes()],
+        bump,
+    )]
+    pub proposal: Account<'info, Proposal>,
+    // Vote PDA, one per user and proposal
+    #[account(
+        init,
+        payer = owner,
+        seeds = [
+            Vote::seed(),
+            &vault_count.to_le_bytes(),
+            &proposal_id.to_le_bytes(),
+            owner.key().as_ref()
+        ],
+        bump,
+        space = 8 + std::mem::size_of::<Vote>(),
+    )]
+    pub vote: Account<'info, Vote>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(vault_count: u64, proposal_id: u64)]
+pub struct ExecuteProposal<'info> {
+    // Anyone may execute a passed proposal
+    pub payer: Signer<'info>,
+    // Vault that holds state
+    #[account(
+        mut,
+        seeds = [Vault::seed(), &vault_count.to_le_bytes()],
+        bump,
+    )]
+    pub vault: Account<'info, Vault>,
+    // Proposal PDA
+    #[account(
+        mut,
+        seeds = [Proposal::seed(), &vault_count.to_le_bytes(), &proposal_id.to_le_bytes()],
+        bump,
+    )]
+    pub proposal: Account<'info, Proposal>,
+}
@@ -4,3 +4,9 @@ pub mod vault;
 pub use vault::*;
 pub mod user;
 pub use user::*;
+pub mod share;
+pub use share::*;
+pub mod waitlist;
+pub use waitlist::*;
+pub mod governance;
+pub use governance::*;
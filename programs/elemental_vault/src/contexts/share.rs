@@ -0,0 +1,249 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token::{Mint, Token, TokenAccount},
+};
+
+use crate::error::ErrorCode;
+use crate::state::{Vault, VaultMode, WithdrawRequest};
+
+#[derive(Accounts)]
+#[instruction(vault_count: u64)]
+pub struct InitShareMint<'info> {
+    // Vault creator or authority
+    #[account(mut)]
+    pub initializer: Signer<'info>,
+    // Vault that holds state
+    #[account(
+        mut,
+        seeds = [Vault::seed(), &vault_count.to_le_bytes()],
+        bump,
+        constraint = vault.mode == VaultMode::Perpetual @ ErrorCode::InvalidVaultMode,
+        constraint = vault.base_mint == base_mint.key() @ ErrorCode::InvalidMint,
+        constraint = vault.authority == initializer.key()
+            || vault.creator == initializer.key() @ ErrorCode::Unauthorized
+    )]
+    pub vault: Account<'info, Vault>,
+    // Share mint owned by the vault
+    #[account(
+        init,
+        payer = initializer,
+        seeds = [Vault::share_seed(), &vault_count.to_le_bytes()],
+        bump,
+        mint::decimals = base_mint.decimals,
+        mint::authority = vault,
+    )]
+    pub share_mint: Account<'info, Mint>,
+    // The base mint of the vault
+    pub base_mint: Account<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(vault_count: u64)]
+pub struct ShareDeposit<'info> {
+    // User's wallet
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    // User's base mint ATA
+    #[account(
+        mut,
+        associated_token::mint = base_mint,
+        associated_token::authority = owner,
+    )]
+    pub source_ata: Account<'info, TokenAccount>,
+    // User's share ATA
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = share_mint,
+        associated_token::authority = owner,
+    )]
+    pub share_ata: Account<'info, TokenAccount>,
+    // Vault ATA to store base mint token.
+    #[account(
+        mut,
+        associated_token::mint = base_mint,
+        associated_token::authority = vault
+    )]
+    pub vault_ata: Account<'info, TokenAccount>,
+    // Vault that holds state
+    #[account(
+        mut,
+        seeds = [Vault::seed(), &vault_count.to_le_bytes()],
+        bump,
+        constraint = vault.mode == VaultMode::Perpetual @ ErrorCode::InvalidVaultMode,
+        constraint = vault.base_mint == base_mint.key() @ ErrorCode::InvalidMint,
+        constraint = vault.share_mint == share_mint.key() @ ErrorCode::InvalidMint
+    )]
+    pub vault: Account<'info, Vault>,
+    // Share mint owned by the vault
+    #[account(mut)]
+    pub share_mint: Account<'info, Mint>,
+    // The base mint of the vault
+    pub base_mint: Account<'info, Mint>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(vault_count: u64)]
+pub struct ShareWithdraw<'info> {
+    // User's wallet
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    // User's base mint ATA
+    #[account(
+        mut,
+        associated_token::mint = base_mint,
+        associated_token::authority = owner,
+    )]
+    pub destination_ata: Account<'info, TokenAccount>,
+    // User's share ATA
+    #[account(
+        mut,
+        associated_token::mint = share_mint,
+        associated_token::authority = owner,
+    )]
+    pub share_ata: Account<'info, TokenAccount>,
+    // Vault ATA to store base mint token.
+    #[account(
+        mut,
+        associated_token::mint = base_mint,
+        associated_token::authority = vault
+    )]
+    pub vault_ata: Account<'info, TokenAccount>,
+    // Vault that holds state
+    #[account(
+        mut,
+        seeds = [Vault::seed(), &vault_count.to_le_bytes()],
+        bump,
+        constraint = vault.mode == VaultMode::Perpetual @ ErrorCode::InvalidVaultMode,
+        constraint = vault.base_mint == base_mint.key() @ ErrorCode::InvalidMint,
+        constraint = vault.share_mint == share_mint.key() @ ErrorCode::InvalidMint
+    )]
+    pub vault: Account<'info, Vault>,
+    // Share mint owned by the vault
+    #[account(mut)]
+    pub share_mint: Account<'info, Mint>,
+    // The base mint of the vault
+    pub base_mint: Account<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(vault_count: u64)]
+pub struct ReportTotalAssets<'info> {
+    // Vault authority
+    pub authority: Signer<'info>,
+    // Vault that holds state
+    #[account(
+        mut,
+        seeds = [Vault::seed(), &vault_count.to_le_bytes()],
+        bump,
+        constraint = vault.mode == VaultMode::Perpetual @ ErrorCode::InvalidVaultMode,
+        constraint = vault.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub vault: Account<'info, Vault>,
+}
+
+#[derive(Accounts)]
+#[instruction(vault_count: u64)]
+pub struct RequestWithdraw<'info> {
+    // User's wallet
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    // User's share ATA
+    #[account(
+        mut,
+        associated_token::mint = share_mint,
+        associated_token::authority = owner,
+    )]
+    pub share_ata: Account<'info, TokenAccount>,
+    // Vault ATA to store base mint token.
+    #[account(
+        associated_token::mint = base_mint,
+        associated_token::authority = vault
+    )]
+    pub vault_ata: Account<'info, TokenAccount>,
+    // Vault that holds state
+    #[account(
+        mut,
+        seeds = [Vault::seed(), &vault_count.to_le_bytes()],
+        bump,
+        constraint = vault.mode == VaultMode::Perpetual @ ErrorCode::InvalidVaultMode,
+        constraint = vault.base_mint == base_mint.key() @ ErrorCode::InvalidMint,
+        constraint = vault.share_mint == share_mint.key() @ ErrorCode::InvalidMint
+    )]
+    pub vault: Account<'info, Vault>,
+    // Withdraw request PDA
+    #[account(
+        init,
+        payer = owner,
+        seeds = [
+            WithdrawRequest::seed(),
+            &vault_count.to_le_bytes(),
+            &vault.queue_count.to_le_bytes()
+        ],
+        bump,
+        space = 8 + std::mem::size_of::<WithdrawRequest>(),
+    )]
+    pub withdraw_request: Account<'info, WithdrawRequest>,
+    // Share mint owned by the vault
+    #[account(mut)]
+    pub share_mint: Account<'info, Mint>,
+    // The base mint of the vault
+    pub base_mint: Account<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(vault_count: u64, request_id: u64)]
+pub struct ClaimWithdraw<'info> {
+    // User's wallet
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    // User's base mint ATA
+    #[account(
+        mut,
+        associated_token::mint = base_mint,
+        associated_token::authority = owner,
+    )]
+    pub destination_ata: Account<'info, TokenAccount>,
+    // Vault ATA to store base mint token.
+    #[account(
+        mut,
+        associated_token::mint = base_mint,
+        associated_token::authority = vault
+    )]
+    pub vault_ata: Account<'info, TokenAccount>,
+    // Vault that holds state
+    #[account(
+        mut,
+        seeds = [Vault::seed(), &vault_count.to_le_bytes()],
+        bump,
+        constraint = vault.base_mint == base_mint.key() @ ErrorCode::InvalidMint
+    )]
+    pub vault: Account<'info, Vault>,
+    // Withdraw request PDA
+    #[account(
+        mut,
+        seeds = [
+            WithdrawRequest::seed(),
+            &vault_count.to_le_bytes(),
+            &request_id.to_le_bytes()
+        ],
+        bump,
+        constraint = withdraw_request.owner == owner.key() @ ErrorCode::Unauthorized,
+        close = owner
+    )]
+    pub withdraw_request: Account<'info, WithdrawRequest>,
+    // The base mint of the vault
+    pub base_mint: Account<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
@@ -4,11 +4,11 @@ use anchor_spl::{
     token::{Mint, Token, TokenAccount},
 };
 
-use crate::state::{Vault, User};
 use crate::error::ErrorCode;
+use crate::state::{Credential, Global, Tranche, User, Vault, VaultMode, WaitlistEntry};
 
 #[derive(Accounts)]
-#[instruction(vault_count: u64, amount: u64)]
+#[instruction(vault_count: u64, amount: u64, tranche_index: u8)]
 pub struct InitOrDepositUser<'info> {
     // User's wallet
     #[account(mut)]
@@ -29,17 +29,37 @@ pub struct InitOrDepositUser<'info> {
     pub destination_ata: Account<'info, TokenAccount>,
     // Vault that holds state
     #[account(
-        mut, seeds = [Vault::seed(), &vault_count.to_le_bytes()], bump)]
+        mut, seeds = [Vault::seed(), &vault_count.to_le_bytes()], bump,
+        constraint = vault.mode == VaultMode::FixedTerm @ ErrorCode::InvalidVaultMode
+    )]
     pub vault: Account<'info, Vault>,
     // User PDA
     #[account(
-        init_if_needed, 
+        init_if_needed,
         payer = owner,
         seeds = [User::seed(), &vault_count.to_le_bytes(), owner.key().as_ref()],
         bump,
         space = 8 + std::mem::size_of::<User>(),
     )]
     pub user: Account<'info, User>,
+    // Tranche to deposit into, required for tranched vaults
+    #[account(
+        mut,
+        seeds = [Tranche::seed(), &vault_count.to_le_bytes(), &[tranche_index]],
+        bump,
+    )]
+    pub tranche: Option<Account<'info, Tranche>>,
+    /// CHECK: Price account of the collateral, validated against the vault
+    pub collateral_oracle: Option<UncheckedAccount<'info>>,
+    // KYC credential of the user, required when the vault has a KYC issuer
+    #[account(
+        seeds = [Credential::seed(), vault.kyc_issuer.as_ref(), owner.key().as_ref()],
+        bump,
+    )]
+    pub credential: Option<Account<'info, Credential>>,
+    // Global State, to check the issuer of the credential is still an attestor
+    #[account(seeds = [Global::seed()], bump)]
+    pub global: Account<'info, Global>,
     // The base mint of the vault
     pub base_mint: Account<'info, Mint>,
     pub associated_token_program: Program<'info, AssociatedToken>,
@@ -47,6 +67,14 @@ pub struct InitOrDepositUser<'info> {
     pub system_program: Program<'info, System>,
 }
 
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct AllowlistProof {
+    // Deposit cap of the leaf, 0 for no cap
+    pub cap: u64,
+    // Sibling hashes from the leaf to the root
+    pub proof: Vec<[u8; 32]>,
+}
+
 #[derive(Accounts)]
 #[instruction(vault_count: u64)]
 pub struct UserWithdraw<'info> {
@@ -58,7 +86,6 @@ pub struct UserWithdraw<'info> {
         mut,
         associated_token::mint = base_mint,
         associated_token::authority = vault,
-        constraint = source_ata.amount >= user.amount @ ErrorCode::VaultNotReady
     )]
     pub source_ata: Account<'info, TokenAccount>,
     // User's ATA
@@ -69,6 +96,267 @@ pub struct UserWithdraw<'info> {
     )]
     pub destination_ata: Account<'info, TokenAccount>,
     // Vault that holds state
+    #[account(
+        mut, seeds = [Vault::seed(), &vault_count.to_le_bytes()], bump,
+        constraint = vault.base_mint == base_mint.key() @ ErrorCode::InvalidMint,
+        constraint = vault.mode == VaultMode::FixedTerm @ ErrorCode::InvalidVaultMode
+    )]
+    pub vault: Account<'info, Vault>,
+    // User PDA
+    #[account(
+        mut,
+        seeds = [User::seed(), &vault_count.to_le_bytes(), owner.key().as_ref()],
+        bump,
+    )]
+    pub user: Account<'info, User>,
+    // Tranche of the user, required for tranched vaults
+    #[account(
+        mut,
+        seeds = [Tranche::seed(), &vault_count.to_le_bytes(), &[user.tranche]],
+        bump,
+    )]
+    pub tranche: Option<Account<'info, Tranche>>,
+    // First-loss capital to top up a shortfall
+    #[account(
+        mut,
+        seeds = [Vault::first_loss_seed(), &vault_count.to_le_bytes()],
+        bump,
+    )]
+    pub first_loss_ata: Option<Account<'info, TokenAccount>>,
+    // The base mint of the vault
+    pub base_mint: Account<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(vault_count: u64)]
+pub struct CancelDeposit<'info> {
+    // User's wallet
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    // Vault ATA to store base mint token.
+    #[account(
+        mut,
+        associated_token::mint = base_mint,
+        associated_token::authority = vault,
+    )]
+    pub source_ata: Account<'info, TokenAccount>,
+    // User's ATA
+    #[account(
+        mut,
+        associated_token::mint = base_mint,
+        associated_token::authority = owner
+    )]
+    pub destination_ata: Account<'info, TokenAccount>,
+    // Vault that holds state
+    #[account(
+        mut, seeds = [Vault::seed(), &vault_count.to_le_bytes()], bump,
+        constraint = vault.base_mint == base_mint.key() @ ErrorCode::InvalidMint,
+        constraint = vault.mode == VaultMode::FixedTerm @ ErrorCode::InvalidVaultMode
+    )]
+    pub vault: Account<'info, Vault>,
+    // User PDA
+    #[account(
+        mut,
+        seeds = [User::seed(), &vault_count.to_le_bytes(), owner.key().as_ref()],
+        bump,
+    )]
+    pub user: Account<'info, User>,
+    // Tranche of the user, required for tranched vaults
+    #[account(
+        mut,
+        seeds = [Tranche::seed(), &vault_count.to_le_bytes(), &[user.tranche]],
+        bump,
+    )]
+    pub tranche: Option<Account<'info, Tranche>>,
+    // Waitlist entry at the head of the queue, promoted into the freed capacity
+    #[account(
+        mut,
+        seeds = [
+            WaitlistEntry::seed(),
+            &vault_count.to_le_bytes(),
+            &vault.waitlist_head.to_le_bytes()
+        ],
+        bump,
+    )]
+    pub waitlist_entry: Option<Account<'info, WaitlistEntry>>,
+    #[account(mut)]
+    /// CHECK: constraint in handler against the waitlist entry
+    pub waitlist_owner: Option<AccountInfo<'info>>,
+    // User PDA of the waitlisted wallet
+    #[account(mut)]
+    pub waitlist_user: Option<Account<'info, User>>,
+    // The base mint of the vault
+    pub base_mint: Account<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(vault_count: u64)]
+pub struct OptOutExtension<'info> {
+    // User's wallet
+    pub owner: Signer<'info>,
+    // Vault that holds state
+    #[account(
+        mut,
+        seeds = [Vault::seed(), &vault_count.to_le_bytes()],
+        bump,
+    )]
+    pub vault: Account<'info, Vault>,
+    // User PDA
+    #[account(
+        mut,
+        seeds = [User::seed(), &vault_count.to_le_bytes(), owner.key().as_ref()],
+        bump,
+    )]
+    pub user: Account<'info, User>,
+}
+
+#[derive(Accounts)]
+#[instruction(vault_count: u64)]
+pub struct ClaimCollateral<'info> {
+    // User's wallet
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    // User's collateral mint ATA
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = collateral_mint,
+        associated_token::authority = owner
+    )]
+    pub destination_ata: Account<'info, TokenAccount>,
+    // Vault that holds state
+    #[account(
+        mut, seeds = [Vault::seed(), &vault_count.to_le_bytes()], bump,
+        constraint = vault.collateral_mint == collateral_mint.key() @ ErrorCode::InvalidMint
+    )]
+    pub vault: Account<'info, Vault>,
+    // Collateral token account owned by the vault
+    #[account(
+        mut,
+        seeds = [Vault::collateral_seed(), &vault_count.to_le_bytes()],
+        bump,
+    )]
+    pub collateral_ata: Account<'info, TokenAccount>,
+    // User PDA
+    #[account(
+        mut,
+        seeds = [User::seed(), &vault_count.to_le_bytes(), owner.key().as_ref()],
+        bump,
+    )]
+    pub user: Account<'info, User>,
+    // Tranche of the user, required for tranched vaults
+    #[account(
+        seeds = [Tranche::seed(), &vault_count.to_le_bytes(), &[user.tranche]],
+        bump,
+    )]
+    pub tranche: Option<Account<'info, Tranche>>,
+    /// CHECK: Price account of the collateral, validated against the vault
+    pub collateral_oracle: Option<UncheckedAccount<'info>>,
+    // The collateral mint of the vault
+    pub collateral_mint: Account<'info, Mint>,
+    // The base mint of the vault
+    #[account(constraint = vault.base_mint == base_mint.key() @ ErrorCode::InvalidMint)]
+    pub base_mint: Account<'info, Mint>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(vault_count: u64)]
+pub struct SweepUser<'info> {
+    // Anyone may sweep once the vault can be closed
+    pub payer: Signer<'info>,
+    #[account(mut)]
+    /// CHECK: constraint in user
+    pub owner: AccountInfo<'info>,
+    // User PDA
+    #[account(
+        mut,
+        seeds = [User::seed(), &vault_count.to_le_bytes(), owner.key().as_ref()],
+        bump,
+        constraint = user.owner == owner.key() @ ErrorCode::Unauthorized,
+    )]
+    pub user: Account<'info, User>,
+    #[account(
+        mut,
+        seeds = [Vault::seed(), &vault_count.to_le_bytes()],
+        bump,
+    )]
+    /// CHECK: Vault that holds state, may already be closed
+    pub vault: UncheckedAccount<'info>,
+    // Tranche of the user, required for tranched vaults
+    #[account(
+        mut,
+        seeds = [Tranche::seed(), &vault_count.to_le_bytes(), &[user.tranche]],
+        bump,
+    )]
+    pub tranche: Option<Account<'info, Tranche>>,
+    // Vault ATA, required to pay an unclaimed balance
+    #[account(
+        mut,
+        associated_token::mint = base_mint,
+        associated_token::authority = vault,
+    )]
+    pub source_ata: Option<Account<'info, TokenAccount>>,
+    // Owner's ATA, required to pay an unclaimed balance
+    #[account(
+        mut,
+        associated_token::mint = base_mint,
+        associated_token::authority = owner,
+    )]
+    pub destination_ata: Option<Account<'info, TokenAccount>>,
+    // The base mint of the vault, required to pay an unclaimed balance or collateral
+    pub base_mint: Option<Account<'info, Mint>>,
+    // Collateral token account owned by the vault, required to pay unclaimed collateral
+    #[account(
+        mut,
+        seeds = [Vault::collateral_seed(), &vault_count.to_le_bytes()],
+        bump,
+    )]
+    pub collateral_ata: Option<Account<'info, TokenAccount>>,
+    // Owner's collateral ATA, required to pay unclaimed collateral
+    #[account(
+        mut,
+        associated_token::mint = collateral_mint,
+        associated_token::authority = owner,
+    )]
+    pub collateral_destination_ata: Option<Account<'info, TokenAccount>>,
+    // The collateral mint of the vault, required to pay unclaimed collateral
+    pub collateral_mint: Option<Account<'info, Mint>>,
+    /// CHECK: Price account of the collateral, validated against the vault
+    pub collateral_oracle: Option<UncheckedAccount<'info>>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(vault_count: u64)]
+pub struct ClaimExcess<'info> {
+    // Anyone may allocate a user once the allocation is finalized
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut)]
+    /// CHECK: constraint in user
+    pub owner: AccountInfo<'info>,
+    // Vault ATA to store base mint token.
+    #[account(
+        mut,
+        associated_token::mint = base_mint,
+        associated_token::authority = vault,
+    )]
+    pub source_ata: Account<'info, TokenAccount>,
+    // Owner's ATA
+    #[account(
+        mut,
+        associated_token::mint = base_mint,
+        associated_token::authority = owner
+    )]
+    pub destination_ata: Account<'info, TokenAccount>,
+    // Vault that holds state
     #[account(
         mut, seeds = [Vault::seed(), &vault_count.to_le_bytes()], bump,
         constraint = vault.base_mint == base_mint.key() @ ErrorCode::InvalidMint
@@ -79,7 +367,7 @@ pub struct UserWithdraw<'info> {
         mut,
         seeds = [User::seed(), &vault_count.to_le_bytes(), owner.key().as_ref()],
         bump,
-        close = owner
+        constraint = user.owner == owner.key() @ ErrorCode::Unauthorized,
     )]
     pub user: Account<'info, User>,
     // The base mint of the vault
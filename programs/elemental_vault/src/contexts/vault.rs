@@ -5,8 +5,11 @@ use anchor_spl::{
     token::{Mint, Token, TokenAccount},
 };
 
-use crate::state::{Vault, Global};
 use crate::error::ErrorCode;
+use crate::state::{
+    AmortizationStyle, AssetSource, CouponFrequency, Global, PenaltyRecipient, Tranche, Vault,
+    VaultMode, MAX_TIERS,
+};
 
 #[derive(Accounts)]
 #[instruction(vault_count: u64)]
@@ -49,7 +52,7 @@ pub struct UpdateAuthority<'info> {
         mut,
         seeds = [Vault::seed(), &vault_count.to_le_bytes()],
         bump,
-        constraint = vault.authority == current_authority.key() 
+        constraint = vault.authority == current_authority.key()
     )]
     pub vault: Account<'info, Vault>,
     pub system_program: Program<'info, System>,
@@ -87,6 +90,215 @@ pub struct AuthorityWithdraw<'info> {
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+#[instruction(vault_count: u64)]
+pub struct Repay<'info> {
+    // vault authority
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        associated_token::mint = base_mint,
+        associated_token::authority = authority
+    )]
+    pub source_ata: Account<'info, TokenAccount>,
+    // vault that holds state
+    #[account(
+        mut, seeds = [Vault::seed(), &vault_count.to_le_bytes()], bump,
+        constraint = vault.authority == authority.key() @ ErrorCode::Unauthorized,
+        constraint = vault.base_mint == base_mint.key() @ ErrorCode::InvalidMint
+    )]
+    pub vault: Account<'info, Vault>,
+    // vault ATA to store base mint token.
+    #[account(
+        mut,
+        associated_token::mint = base_mint,
+        associated_token::authority = vault
+    )]
+    pub vault_ata: Account<'info, TokenAccount>,
+    // The base mint of the vault
+    pub base_mint: Account<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(vault_count: u64, index: u8)]
+pub struct InitOrUpdateTranche<'info> {
+    // Vault creator or authority
+    #[account(mut)]
+    pub initializer: Signer<'info>,
+    // Vault that holds state
+    #[account(
+        mut,
+        seeds = [Vault::seed(), &vault_count.to_le_bytes()],
+        bump,
+        constraint = vault.mode == VaultMode::FixedTerm @ ErrorCode::InvalidVaultMode,
+        constraint = vault.authority == initializer.key()
+            || vault.creator == initializer.key() @ ErrorCode::Unauthorized
+    )]
+    pub vault: Account<'info, Vault>,
+    // Tranche that holds state
+    #[account(
+        init_if_needed,
+        payer = initializer,
+        seeds = [Tranche::seed(), &vault_count.to_le_bytes(), &[index]],
+        bump,
+        space = 8 + std::mem::size_of::<Tranche>(),
+    )]
+    pub tranche: Account<'info, Tranche>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(vault_count: u64)]
+pub struct SettleVault<'info> {
+    // Anyone may settle once the vault matures
+    pub payer: Signer<'info>,
+    // Vault that holds state
+    #[account(
+        mut,
+        seeds = [Vault::seed(), &vault_count.to_le_bytes()],
+        bump,
+        constraint = vault.mode == VaultMode::FixedTerm @ ErrorCode::InvalidVaultMode,
+    )]
+    pub vault: Account<'info, Vault>,
+    // vault ATA to store base mint token.
+    #[account(
+        associated_token::mint = vault.base_mint,
+        associated_token::authority = vault
+    )]
+    pub vault_ata: Account<'info, TokenAccount>,
+    // First-loss capital counted toward settlement
+    #[account(
+        seeds = [Vault::first_loss_seed(), &vault_count.to_le_bytes()],
+        bump,
+    )]
+    pub first_loss_ata: Option<Account<'info, TokenAccount>>,
+}
+
+#[derive(Accounts)]
+#[instruction(vault_count: u64)]
+pub struct FinalizeAllocation<'info> {
+    // Anyone may finalize once deposits close
+    pub payer: Signer<'info>,
+    // Vault that holds state
+    #[account(
+        mut,
+        seeds = [Vault::seed(), &vault_count.to_le_bytes()],
+        bump,
+    )]
+    pub vault: Account<'info, Vault>,
+}
+
+#[derive(Accounts)]
+#[instruction(vault_count: u64)]
+pub struct ProposeExtension<'info> {
+    // vault authority
+    pub authority: Signer<'info>,
+    // Vault that holds state
+    #[account(
+        mut, seeds = [Vault::seed(), &vault_count.to_le_bytes()], bump,
+        constraint = vault.authority == authority.key() @ ErrorCode::Unauthorized,
+        constraint = vault.mode == VaultMode::FixedTerm @ ErrorCode::InvalidVaultMode
+    )]
+    pub vault: Account<'info, Vault>,
+}
+
+#[derive(Accounts)]
+#[instruction(vault_count: u64)]
+pub struct ApplyExtension<'info> {
+    // Anyone may apply once the opt-out window closes
+    pub payer: Signer<'info>,
+    // Vault that holds state
+    #[account(
+        mut,
+        seeds = [Vault::seed(), &vault_count.to_le_bytes()],
+        bump,
+    )]
+    pub vault: Account<'info, Vault>,
+}
+
+#[derive(Accounts)]
+#[instruction(vault_count: u64)]
+pub struct GetLiabilities<'info> {
+    // Vault that holds state
+    #[account(
+        seeds = [Vault::seed(), &vault_count.to_le_bytes()],
+        bump,
+        constraint = vault.mode == VaultMode::FixedTerm @ ErrorCode::InvalidVaultMode,
+    )]
+    pub vault: Account<'info, Vault>,
+}
+
+#[derive(Accounts)]
+#[instruction(vault_count: u64)]
+pub struct DepositFirstLoss<'info> {
+    // vault authority
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        associated_token::mint = base_mint,
+        associated_token::authority = authority
+    )]
+    pub source_ata: Account<'info, TokenAccount>,
+    // vault that holds state
+    #[account(
+        mut, seeds = [Vault::seed(), &vault_count.to_le_bytes()], bump,
+        constraint = vault.authority == authority.key() @ ErrorCode::Unauthorized,
+        constraint = vault.base_mint == base_mint.key() @ ErrorCode::InvalidMint,
+        constraint = vault.mode == VaultMode::FixedTerm @ ErrorCode::InvalidVaultMode
+    )]
+    pub vault: Account<'info, Vault>,
+    // First-loss token account owned by the vault
+    #[account(
+        init_if_needed,
+        payer = authority,
+        seeds = [Vault::first_loss_seed(), &vault_count.to_le_bytes()],
+        bump,
+        token::mint = base_mint,
+        token::authority = vault,
+    )]
+    pub first_loss_ata: Account<'info, TokenAccount>,
+    // The base mint of the vault
+    pub base_mint: Account<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(vault_count: u64)]
+pub struct ReclaimFirstLoss<'info> {
+    // vault authority
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        associated_token::mint = base_mint,
+        associated_token::authority = authority
+    )]
+    pub destination_ata: Account<'info, TokenAccount>,
+    // vault that holds state
+    #[account(
+        mut, seeds = [Vault::seed(), &vault_count.to_le_bytes()], bump,
+        constraint = vault.authority == authority.key() @ ErrorCode::Unauthorized,
+        constraint = vault.base_mint == base_mint.key() @ ErrorCode::InvalidMint
+    )]
+    pub vault: Account<'info, Vault>,
+    // First-loss token account owned by the vault
+    #[account(
+        mut,
+        seeds = [Vault::first_loss_seed(), &vault_count.to_le_bytes()],
+        bump,
+    )]
+    pub first_loss_ata: Account<'info, TokenAccount>,
+    // The base mint of the vault
+    pub base_mint: Account<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
 #[derive(Accounts)]
 #[instruction(vault_count: u64, authority: Pubkey)]
 pub struct CloseVault<'info> {
@@ -115,7 +327,6 @@ pub struct CloseVault<'info> {
         constraint = vault.authority == authority.key(),
         constraint = vault.base_mint == base_mint.key(),
         constraint = vault.creator == creator.key(),
-        close = creator
     )]
     pub vault: Account<'info, Vault>,
     #[account(mut)]
@@ -123,10 +334,19 @@ pub struct CloseVault<'info> {
     pub creator: AccountInfo<'info>,
     // The base mint of the vault
     pub base_mint: Account<'info, Mint>,
+    // Share mint, required for perpetual vaults
+    #[account(constraint = vault.share_mint == share_mint.key() @ ErrorCode::InvalidMint)]
+    pub share_mint: Option<Account<'info, Mint>>,
     pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
 }
 
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct InitOrUpdateTrancheParam {
+    pub yield_bps: u16,
+    pub vault_capacity: u64,
+    pub min_amount: u64,
+}
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, ZeroCopyAccessor)]
 pub struct InitOrUpdateVaultParam {
@@ -137,4 +357,141 @@ pub struct InitOrUpdateVaultParam {
     pub end_date: Option<u64>,
     pub withdraw_timeframe: Option<u64>,
     pub authority: Option<Pubkey>,
+    pub mode: Option<VaultMode>,
+    pub asset_source: Option<AssetSource>,
+    pub early_withdraw: Option<bool>,
+    pub early_withdraw_penalty_bps: Option<u16>,
+    pub penalty_decay: Option<bool>,
+    pub penalty_recipient: Option<PenaltyRecipient>,
+    pub vesting_period: Option<u64>,
+    pub coupon_frequency: Option<CouponFrequency>,
+    pub amortization: Option<AmortizationStyle>,
+    pub instalment_count: Option<u64>,
+    pub collateral_mint: Option<Pubkey>,
+    pub collateral_ratio_bps: Option<u16>,
+    pub collateral_oracle: Option<Pubkey>,
+    pub oracle_max_staleness: Option<u64>,
+    pub oracle_max_conf_bps: Option<u16>,
+    pub maintenance_ratio_bps: Option<u16>,
+    pub liquidation_bounty_bps: Option<u16>,
+    pub require_yield_reserve: Option<bool>,
+    pub grace_period: Option<u64>,
+    pub late_penalty_bps: Option<u16>,
+    pub allowlist_root: Option<[u8; 32]>,
+    pub kyc_issuer: Option<Pubkey>,
+    pub max_per_user: Option<u64>,
+    pub tier_caps: Option<[u64; MAX_TIERS]>,
+    pub oversubscription: Option<bool>,
+    pub deposit_open: Option<u64>,
+    pub deposit_close: Option<u64>,
+    pub governance_quorum_bps: Option<u16>,
+    pub governance_threshold_bps: Option<u16>,
+    pub voting_period: Option<u64>,
+}
+
+#[derive(Accounts)]
+#[instruction(vault_count: u64)]
+pub struct DepositCollateral<'info> {
+    // vault authority
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        associated_token::mint = collateral_mint,
+        associated_token::authority = authority
+    )]
+    pub source_ata: Account<'info, TokenAccount>,
+    // vault that holds state
+    #[account(
+        mut, seeds = [Vault::seed(), &vault_count.to_le_bytes()], bump,
+        constraint = vault.authority == authority.key() @ ErrorCode::Unauthorized,
+        constraint = vault.collateral_mint == collateral_mint.key() @ ErrorCode::InvalidMint,
+        constraint = vault.mode == VaultMode::FixedTerm @ ErrorCode::InvalidVaultMode
+    )]
+    pub vault: Account<'info, Vault>,
+    // Collateral token account owned by the vault
+    #[account(
+        init_if_needed,
+        payer = authority,
+        seeds = [Vault::collateral_seed(), &vault_count.to_le_bytes()],
+        bump,
+        token::mint = collateral_mint,
+        token::authority = vault,
+    )]
+    pub collateral_ata: Account<'info, TokenAccount>,
+    // The collateral mint of the vault
+    pub collateral_mint: Account<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(vault_count: u64)]
+pub struct ReleaseCollateral<'info> {
+    // vault authority
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        associated_token::mint = collateral_mint,
+        associated_token::authority = authority
+    )]
+    pub destination_ata: Account<'info, TokenAccount>,
+    // vault that holds state
+    #[account(
+        mut, seeds = [Vault::seed(), &vault_count.to_le_bytes()], bump,
+        constraint = vault.authority == authority.key() @ ErrorCode::Unauthorized,
+        constraint = vault.collateral_mint == collateral_mint.key() @ ErrorCode::InvalidMint
+    )]
+    pub vault: Account<'info, Vault>,
+    // Collateral token account owned by the vault
+    #[account(
+        mut,
+        seeds = [Vault::collateral_seed(), &vault_count.to_le_bytes()],
+        bump,
+    )]
+    pub collateral_ata: Account<'info, TokenAccount>,
+    // The collateral mint of the vault
+    pub collateral_mint: Account<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(vault_count: u64)]
+pub struct Liquidate<'info> {
+    // Any keeper
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+    // Keeper's collateral mint ATA
+    #[account(
+        init_if_needed,
+        payer = keeper,
+        associated_token::mint = collateral_mint,
+        associated_token::authority = keeper
+    )]
+    pub keeper_ata: Account<'info, TokenAccount>,
+    // vault that holds state
+    #[account(
+        mut, seeds = [Vault::seed(), &vault_count.to_le_bytes()], bump,
+        constraint = vault.collateral_mint == collateral_mint.key() @ ErrorCode::InvalidMint,
+        constraint = vault.base_mint == base_mint.key() @ ErrorCode::InvalidMint
+    )]
+    pub vault: Account<'info, Vault>,
+    // Collateral token account owned by the vault
+    #[account(
+        mut,
+        seeds = [Vault::collateral_seed(), &vault_count.to_le_bytes()],
+        bump,
+    )]
+    pub collateral_ata: Account<'info, TokenAccount>,
+    /// CHECK: Price account of the collateral, validated against the vault
+    pub collateral_oracle: Option<UncheckedAccount<'info>>,
+    // The collateral mint of the vault
+    pub collateral_mint: Account<'info, Mint>,
+    // The base mint of the vault
+    pub base_mint: Account<'info, Mint>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
 }
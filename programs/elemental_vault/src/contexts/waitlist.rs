@@ -0,0 +1,155 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Mint, Token, TokenAccount};
+
+use crate::error::ErrorCode;
+use crate::state::{User, Vault, VaultMode, WaitlistEntry};
+
+#[derive(Accounts)]
+#[instruction(vault_count: u64)]
+pub struct JoinWaitlist<'info> {
+    // User's wallet
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    // User's base mint ATA
+    #[account(
+        mut,
+        associated_token::mint = base_mint,
+        associated_token::authority = owner,
+    )]
+    pub source_ata: Account<'info, TokenAccount>,
+    // Vault ATA to store base mint token.
+    #[account(
+        mut,
+        associated_token::mint = base_mint,
+        associated_token::authority = vault
+    )]
+    pub vault_ata: Account<'info, TokenAccount>,
+    // Vault that holds state
+    #[account(
+        mut,
+        seeds = [Vault::seed(), &vault_count.to_le_bytes()],
+        bump,
+        constraint = vault.mode == VaultMode::FixedTerm @ ErrorCode::InvalidVaultMode,
+        constraint = vault.base_mint == base_mint.key() @ ErrorCode::InvalidMint
+    )]
+    pub vault: Account<'info, Vault>,
+    // Waitlist entry PDA
+    #[account(
+        init,
+        payer = owner,
+        seeds = [
+            WaitlistEntry::seed(),
+            &vault_count.to_le_bytes(),
+            &vault.waitlist_count.to_le_bytes()
+        ],
+        bump,
+        space = 8 + std::mem::size_of::<WaitlistEntry>(),
+    )]
+    pub waitlist_entry: Account<'info, WaitlistEntry>,
+    // User PDA, funded by the owner so the promotion needs no rent
+    #[account(
+        init_if_needed,
+        payer = owner,
+        seeds = [User::seed(), &vault_count.to_le_bytes(), owner.key().as_ref()],
+        bump,
+        space = 8 + std::mem::size_of::<User>(),
+    )]
+    pub user: Account<'info, User>,
+    // The base mint of the vault
+    pub base_mint: Account<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(vault_count: u64)]
+pub struct PromoteWaitlist<'info> {
+    // Anyone may promote the head of the waitlist
+    pub payer: Signer<'info>,
+    #[account(mut)]
+    /// CHECK: constraint in waitlist entry
+    pub owner: AccountInfo<'info>,
+    // Vault that holds state
+    #[account(
+        mut,
+        seeds = [Vault::seed(), &vault_count.to_le_bytes()],
+        bump,
+        constraint = vault.mode == VaultMode::FixedTerm @ ErrorCode::InvalidVaultMode
+    )]
+    pub vault: Account<'info, Vault>,
+    // Waitlist entry at the head of the queue
+    #[account(
+        mut,
+        seeds = [
+            WaitlistEntry::seed(),
+            &vault_count.to_le_bytes(),
+            &vault.waitlist_head.to_le_bytes()
+        ],
+        bump,
+        constraint = waitlist_entry.owner == owner.key() @ ErrorCode::Unauthorized,
+        close = owner
+    )]
+    pub waitlist_entry: Account<'info, WaitlistEntry>,
+    // User PDA of the waitlisted wallet, created when joining
+    #[account(
+        mut,
+        seeds = [User::seed(), &vault_count.to_le_bytes(), owner.key().as_ref()],
+        bump,
+    )]
+    pub user: Account<'info, User>,
+}
+
+#[derive(Accounts)]
+#[instruction(vault_count: u64, entry_id: u64)]
+pub struct RefundWaitlist<'info> {
+    // Owner leaving the waitlist, or anyone once deposits close
+    pub payer: Signer<'info>,
+    #[account(mut)]
+    /// CHECK: constraint in waitlist entry
+    pub owner: AccountInfo<'info>,
+    // Owner's ATA
+    #[account(
+        mut,
+        associated_token::mint = base_mint,
+        associated_token::authority = owner
+    )]
+    pub destination_ata: Account<'info, TokenAccount>,
+    // Vault ATA to store base mint token.
+    #[account(
+        mut,
+        associated_token::mint = base_mint,
+        associated_token::authority = vault
+    )]
+    pub vault_ata: Account<'info, TokenAccount>,
+    // Vault that holds state
+    #[account(
+        mut,
+        seeds = [Vault::seed(), &vault_count.to_le_bytes()],
+        bump,
+        constraint = vault.base_mint == base_mint.key() @ ErrorCode::InvalidMint
+    )]
+    pub vault: Account<'info, Vault>,
+    // Waitlist entry PDA
+    #[account(
+        mut,
+        seeds = [
+            WaitlistEntry::seed(),
+            &vault_count.to_le_bytes(),
+            &entry_id.to_le_bytes()
+        ],
+        bump,
+        constraint = waitlist_entry.owner == owner.key() @ ErrorCode::Unauthorized,
+    )]
+    pub waitlist_entry: Account<'info, WaitlistEntry>,
+    // User PDA of the waitlisted wallet, required before deposits close
+    #[account(
+        mut,
+        seeds = [User::seed(), &vault_count.to_le_bytes(), owner.key().as_ref()],
+        bump,
+    )]
+    pub user: Option<Account<'info, User>>,
+    // The base mint of the vault
+    pub base_mint: Account<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
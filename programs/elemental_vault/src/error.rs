@@ -28,4 +28,102 @@ pub enum ErrorCode {
     InvalidMint,
     #[msg("Overflow detected")]
     Overflow,
+    #[msg("Instruction not supported by vault mode")]
+    InvalidVaultMode,
+    #[msg("Share mint already initialized")]
+    ShareMintInitialized,
+    #[msg("Vault does not hold enough liquidity")]
+    InsufficientLiquidity,
+    #[msg("Amount must be greater than zero")]
+    ZeroAmount,
+    #[msg("Shares still outstanding")]
+    SharesOutstanding,
+    #[msg("Withdraw request not filled yet")]
+    RequestNotFilled,
+    #[msg("Withdraw requests must be claimed first")]
+    WithdrawQueueOutstanding,
+    #[msg("Early withdrawal not enabled for vault")]
+    EarlyWithdrawDisabled,
+    #[msg("Vault not active")]
+    VaultNotActive,
+    #[msg("Basis points must not exceed 10000")]
+    InvalidBps,
+    #[msg("Vesting period must fit within withdraw timeframe")]
+    InvalidVestingPeriod,
+    #[msg("Nothing to claim")]
+    NothingToClaim,
+    #[msg("Coupons are no longer claimable after end date")]
+    CouponClosed,
+    #[msg("Invalid amortization schedule")]
+    InvalidAmortization,
+    #[msg("All instalments already repaid")]
+    InstalmentsRepaid,
+    #[msg("Invalid tranche")]
+    InvalidTranche,
+    #[msg("Vault must be settled first")]
+    VaultNotSettled,
+    #[msg("Vault already settled")]
+    VaultSettled,
+    #[msg("Users have not all been paid")]
+    UsersOutstanding,
+    #[msg("First-loss capital must be reclaimed first")]
+    FirstLossOutstanding,
+    #[msg("First-loss account is required")]
+    FirstLossRequired,
+    #[msg("Vault is not sufficiently collateralized")]
+    InsufficientCollateral,
+    #[msg("Collateral can no longer be changed")]
+    CollateralLocked,
+    #[msg("Collateral must be released or claimed first")]
+    CollateralOutstanding,
+    #[msg("Invalid oracle account")]
+    InvalidOracle,
+    #[msg("Oracle price is unavailable")]
+    OraclePriceUnavailable,
+    #[msg("Oracle price is stale")]
+    OraclePriceStale,
+    #[msg("Oracle confidence interval is too wide")]
+    OracleConfidenceTooWide,
+    #[msg("Vault collateral is above the maintenance ratio")]
+    CollateralHealthy,
+    #[msg("Yield reserve has not been funded")]
+    YieldReserveNotFunded,
+    #[msg("Vault is still within its grace period")]
+    GracePeriod,
+    #[msg("Invalid token account")]
+    InvalidTokenAccount,
+    #[msg("Wallet is not on the allowlist")]
+    NotAllowlisted,
+    #[msg("Amount exceeds the wallet deposit cap")]
+    AmountExceedUserCap,
+    #[msg("Valid KYC credential required")]
+    InvalidCredential,
+    #[msg("Attestor list is full")]
+    AttestorsFull,
+    #[msg("Invalid credential level")]
+    InvalidTier,
+    #[msg("Excess commitment must be allocated first")]
+    AllocationPending,
+    #[msg("Waitlist is not available for this vault")]
+    WaitlistUnavailable,
+    #[msg("Vault has capacity, deposit directly")]
+    VaultNotFull,
+    #[msg("Waitlist deposits must be refunded first")]
+    WaitlistOutstanding,
+    #[msg("Deposit window must close after it opens and no later than start date")]
+    InvalidDepositWindow,
+    #[msg("Deposit window is not open yet")]
+    DepositNotOpen,
+    #[msg("Invalid maturity extension")]
+    InvalidExtension,
+    #[msg("Maturity extension is pending")]
+    ExtensionPending,
+    #[msg("Governance is not enabled for this vault")]
+    GovernanceDisabled,
+    #[msg("Voting on this proposal is closed")]
+    VotingClosed,
+    #[msg("Proposal has not passed")]
+    ProposalNotPassed,
+    #[msg("User has a live vote")]
+    VoteLocked,
 }
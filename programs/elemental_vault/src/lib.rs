@@ -1,12 +1,18 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::transfer_checked;
+use anchor_spl::token::{burn, mint_to, transfer_checked, Burn, MintTo, TransferChecked};
 
 mod contexts;
 mod error;
+mod oracle;
 mod state;
 
 use contexts::*;
 use error::ErrorCode;
+use oracle::Price;
+use state::{
+    AmortizationStyle, AssetSource, PenaltyRecipient, Rounding, Tranche, VaultMode, VaultStatus,
+    MAX_INSTALMENTS, MAX_TIERS, MAX_TRANCHES, PENALTY_SCALE,
+};
 
 declare_id!("Cq4qXN5syKaUt6if8wLH8fNZy2epnsByemJtpbis8awH");
 
@@ -18,6 +24,8 @@ pub mod elemental_vault {
 
     pub fn init_global(ctx: Context<InitGlobal>) -> Result<()> {
         ctx.accounts.global.vault_counter = 0;
+        // ADMIN IS THE UPGRADE AUTHORITY OF THE PROGRAM
+        ctx.accounts.global.admin = ctx.accounts.initializer.key();
         Ok(())
     }
 
@@ -71,9 +79,10 @@ pub mod elemental_vault {
             return err!(ErrorCode::Unauthorized);
         }
 
-        // VAULT STATE NOT UPDATABLE IF FUNDS HAVE BEEN COLLECTED
+        // ONCE FUNDS HAVE BEEN COLLECTED ONLY CHANGES FAVOURABLE TO DEPOSITORS ARE ALLOWED
         if vault.amount_collected != 0 {
-            return err!(ErrorCode::NotUpdatable);
+            let now = (Clock::get()?.unix_timestamp * 1000) as u64;
+            return amend_vault(vault, &params, false, now);
         }
 
         // CAN'T UPDATE ONCE VAULT IS ACTIVE
@@ -98,6 +107,425 @@ pub mod elemental_vault {
             ignore_none
         );
 
+        // DEPOSIT WINDOW DEFAULTS TO CREATION UNTIL START DATE
+        assign_if_some!(params.deposit_open, deposit_open, vault, ignore_none);
+        assign_if_some!(params.deposit_close, deposit_close, vault, ignore_none);
+        if !state::Vault::is_valid_deposit_window(vault) {
+            return err!(ErrorCode::InvalidDepositWindow);
+        }
+
+        // MODE IS FIXED ONCE THE SHARE MINT EXISTS
+        if params.mode.is_some() && vault.share_mint != Pubkey::default() {
+            return err!(ErrorCode::NotUpdatable);
+        }
+        assign_if_some!(params.mode, mode, vault, ignore_none);
+        assign_if_some!(params.asset_source, asset_source, vault, ignore_none);
+
+        // FIXED TERM DEPOSITS ARE MULTIPLES OF A NON-ZERO MIN AMOUNT
+        if vault.mode == VaultMode::FixedTerm && vault.min_amount == 0 {
+            return err!(ErrorCode::InvalidMultiple);
+        }
+
+        if params.early_withdraw_penalty_bps.unwrap_or(0) > 10_000 {
+            return err!(ErrorCode::InvalidBps);
+        }
+        assign_if_some!(params.early_withdraw, early_withdraw, vault, ignore_none);
+        assign_if_some!(
+            params.early_withdraw_penalty_bps,
+            early_withdraw_penalty_bps,
+            vault,
+            ignore_none
+        );
+        assign_if_some!(params.penalty_decay, penalty_decay, vault, ignore_none);
+        assign_if_some!(
+            params.penalty_recipient,
+            penalty_recipient,
+            vault,
+            ignore_none
+        );
+
+        // PAYOUT MUST FULLY VEST BEFORE VAULT CAN BE CLOSED
+        assign_if_some!(params.vesting_period, vesting_period, vault, ignore_none);
+        if vault.vesting_period > vault.withdraw_timeframe {
+            return err!(ErrorCode::InvalidVestingPeriod);
+        }
+
+        // COUPON DATES FOLLOW THE VAULT TERM
+        assign_if_some!(
+            params.coupon_frequency,
+            coupon_frequency,
+            vault,
+            ignore_none
+        );
+        vault.coupon_interval = vault.coupon_frequency.interval();
+        vault.coupon_count = match vault.coupon_interval {
+            0 => 0,
+            interval => (vault.end_date - vault.start_date) / interval,
+        };
+
+        // AMORTIZING VAULTS PAY PRINCIPAL AND INTEREST THROUGH INSTALMENTS ONLY
+        assign_if_some!(params.amortization, amortization, vault, ignore_none);
+        assign_if_some!(
+            params.instalment_count,
+            instalment_count,
+            vault,
+            ignore_none
+        );
+        if vault.amortization != AmortizationStyle::Bullet
+            && (vault.instalment_count == 0
+                || vault.instalment_count > MAX_INSTALMENTS
+                || vault.early_withdraw
+                || vault.coupon_interval != 0
+                || vault.vesting_period != 0)
+        {
+            return err!(ErrorCode::InvalidAmortization);
+        }
+
+        // TRANCHED VAULTS ONLY SUPPORT BULLET REPAYMENT WITHOUT COUPONS
+        if vault.tranche_count > 0
+            && (vault.coupon_interval != 0 || vault.amortization != AmortizationStyle::Bullet)
+        {
+            return err!(ErrorCode::InvalidTranche);
+        }
+
+        // OVERSUBSCRIPTION IS ALLOCATED ON THE VAULT CAPACITY, NOT PER TRANCHE
+        assign_if_some!(
+            params.oversubscription,
+            oversubscription,
+            vault,
+            ignore_none
+        );
+        if vault.oversubscription && vault.tranche_count > 0 {
+            return err!(ErrorCode::InvalidTranche);
+        }
+
+        // YIELD RESERVE IS SIZED ON THE VAULT YIELD, NOT PER TRANCHE
+        assign_if_some!(
+            params.require_yield_reserve,
+            require_yield_reserve,
+            vault,
+            ignore_none
+        );
+        if vault.require_yield_reserve && vault.tranche_count > 0 {
+            return err!(ErrorCode::InvalidTranche);
+        }
+
+        // COLLATERAL MINT IS FIXED ONCE COLLATERAL HAS BEEN LOCKED
+        if params.collateral_mint.is_some() && vault.collateral_deposited != 0 {
+            return err!(ErrorCode::CollateralLocked);
+        }
+        if params.collateral_ratio_bps.is_some() && vault.mode == VaultMode::Perpetual {
+            return err!(ErrorCode::InvalidVaultMode);
+        }
+        assign_if_some!(params.collateral_mint, collateral_mint, vault, ignore_none);
+        assign_if_some!(
+            params.collateral_ratio_bps,
+            collateral_ratio_bps,
+            vault,
+            ignore_none
+        );
+        assign_if_some!(
+            params.collateral_oracle,
+            collateral_oracle,
+            vault,
+            ignore_none
+        );
+        assign_if_some!(
+            params.oracle_max_staleness,
+            oracle_max_staleness,
+            vault,
+            ignore_none
+        );
+        if vault.collateral_oracle != Pubkey::default() && vault.oracle_max_staleness == 0 {
+            return err!(ErrorCode::InvalidOracle);
+        }
+        if params.oracle_max_conf_bps.unwrap_or(0) > 10_000 {
+            return err!(ErrorCode::InvalidBps);
+        }
+        assign_if_some!(
+            params.oracle_max_conf_bps,
+            oracle_max_conf_bps,
+            vault,
+            ignore_none
+        );
+        assign_if_some!(params.allowlist_root, allowlist_root, vault, ignore_none);
+        assign_if_some!(params.kyc_issuer, kyc_issuer, vault, ignore_none);
+        assign_if_some!(params.max_per_user, max_per_user, vault, ignore_none);
+        assign_if_some!(params.tier_caps, tier_caps, vault, ignore_none);
+
+        // LATE PENALTY INTEREST ACCRUES DAILY AFTER THE GRACE PERIOD
+        assign_if_some!(params.grace_period, grace_period, vault, ignore_none);
+        assign_if_some!(
+            params.late_penalty_bps,
+            late_penalty_bps,
+            vault,
+            ignore_none
+        );
+
+        if params.liquidation_bounty_bps.unwrap_or(0) > 10_000 {
+            return err!(ErrorCode::InvalidBps);
+        }
+        assign_if_some!(
+            params.maintenance_ratio_bps,
+            maintenance_ratio_bps,
+            vault,
+            ignore_none
+        );
+        assign_if_some!(
+            params.liquidation_bounty_bps,
+            liquidation_bounty_bps,
+            vault,
+            ignore_none
+        );
+
+        // AMENDMENTS AFTER DEPOSITS NEED DEPOSITOR CONSENT WHEN A QUORUM IS SET
+        if params.governance_quorum_bps.unwrap_or(0) > 10_000
+            || params.governance_threshold_bps.unwrap_or(0) > 10_000
+        {
+            return err!(ErrorCode::InvalidBps);
+        }
+        assign_if_some!(
+            params.governance_quorum_bps,
+            governance_quorum_bps,
+            vault,
+            ignore_none
+        );
+        assign_if_some!(
+            params.governance_threshold_bps,
+            governance_threshold_bps,
+            vault,
+            ignore_none
+        );
+        assign_if_some!(params.voting_period, voting_period, vault, ignore_none);
+        if vault.governance_quorum_bps != 0
+            && (vault.governance_threshold_bps == 0 || vault.voting_period == 0)
+        {
+            return err!(ErrorCode::GovernanceDisabled);
+        }
+
+        Ok(())
+    }
+
+    pub fn create_proposal(
+        ctx: Context<CreateProposal>,
+        vault_count: u64,
+        params: InitOrUpdateVaultParam,
+    ) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        let proposal = &mut ctx.accounts.proposal;
+
+        if vault.governance_quorum_bps == 0 {
+            return err!(ErrorCode::GovernanceDisabled);
+        }
+        // BEFORE DEPOSITS THE AUTHORITY UPDATES THE VAULT DIRECTLY
+        if vault.amount_collected == 0 || vault.status != VaultStatus::Active {
+            return err!(ErrorCode::NotUpdatable);
+        }
+
+        // VOTE WEIGHTS ARE ONLY FINAL ONCE DEPOSITS HAVE CLOSED AND BEEN ALLOCATED
+        let now = (Clock::get()?.unix_timestamp * 1000) as u64;
+        if now < state::Vault::deposit_close(vault) {
+            return err!(ErrorCode::VaultNotReady);
+        }
+        if state::Vault::allocation_pending(vault) {
+            return err!(ErrorCode::AllocationPending);
+        }
+
+        proposal.vault_count = vault_count;
+        proposal.proposal_id = vault.proposal_count;
+        proposal.params = params;
+        proposal.votes_for = 0;
+        proposal.votes_against = 0;
+        proposal.voting_end = now + vault.voting_period;
+        proposal.total_weight = state::Vault::outstanding_principal(vault);
+        proposal.executed = false;
+
+        match vault.proposal_count.checked_add(1) {
+            Some(result) => vault.proposal_count = result,
+            None => return err!(ErrorCode::Overflow),
+        }
+
+        Ok(())
+    }
+
+    pub fn cast_vote(
+        ctx: Context<CastVote>,
+        vault_count: u64,
+        proposal_id: u64,
+        approve: bool,
+    ) -> Result<()> {
+        let user = &mut ctx.accounts.user;
+        let proposal = &mut ctx.accounts.proposal;
+        let vote = &mut ctx.accounts.vote;
+        let now = (Clock::get()?.unix_timestamp * 1000) as u64;
+
+        if proposal.executed || now >= proposal.voting_end {
+            return err!(ErrorCode::VotingClosed);
+        }
+
+        // VOTES ARE WEIGHTED BY THE USER DEPOSIT
+        let tally = match approve {
+            true => &mut proposal.votes_for,
+            false => &mut proposal.votes_against,
+        };
+        match tally.checked_add(user.amount) {
+            Some(result) => *tally = result,
+            None => return err!(ErrorCode::Overflow),
+        }
+
+        vote.vault_count = vault_count;
+        vote.proposal_id = proposal_id;
+        vote.owner = ctx.accounts.owner.key();
+        vote.weight = user.amount;
+        vote.approve = approve;
+
+        // USER CANNOT EXIT WHILE THE BALLOT IS LIVE
+        user.vote_locked_until = user.vote_locked_until.max(proposal.voting_end);
+
+        Ok(())
+    }
+
+    pub fn execute_proposal(
+        ctx: Context<ExecuteProposal>,
+        _vault_count: u64,
+        _proposal_id: u64,
+    ) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        let proposal = &mut ctx.accounts.proposal;
+
+        if proposal.executed {
+            return err!(ErrorCode::VotingClosed);
+        }
+        let now = (Clock::get()?.unix_timestamp * 1000) as u64;
+        if !proposal.has_passed(vault, now) {
+            return err!(ErrorCode::ProposalNotPassed);
+        }
+
+        amend_vault(vault, &proposal.params, true, now)?;
+        proposal.executed = true;
+
+        Ok(())
+    }
+
+    pub fn update_attestor(
+        ctx: Context<UpdateAttestor>,
+        attestor: Pubkey,
+        enabled: bool,
+    ) -> Result<()> {
+        let global = &mut ctx.accounts.global;
+
+        if enabled {
+            if global.is_attestor(&attestor) {
+                return Ok(());
+            }
+            match global
+                .attestors
+                .iter_mut()
+                .find(|key| **key == Pubkey::default())
+            {
+                Some(slot) => *slot = attestor,
+                None => return err!(ErrorCode::AttestorsFull),
+            }
+        } else {
+            for key in global.attestors.iter_mut() {
+                if *key == attestor {
+                    *key = Pubkey::default();
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn issue_credential(
+        ctx: Context<IssueCredential>,
+        wallet: Pubkey,
+        expiry: u64,
+        jurisdiction: [u8; 2],
+        level: u8,
+    ) -> Result<()> {
+        let credential = &mut ctx.accounts.credential;
+
+        if level as usize >= MAX_TIERS {
+            return err!(ErrorCode::InvalidTier);
+        }
+
+        credential.issuer = ctx.accounts.attestor.key();
+        credential.wallet = wallet;
+        credential.expiry = expiry;
+        credential.jurisdiction = jurisdiction;
+        credential.level = level;
+
+        Ok(())
+    }
+
+    pub fn revoke_credential(_ctx: Context<RevokeCredential>) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn set_manual_price(
+        ctx: Context<SetManualPrice>,
+        price: i64,
+        conf: u64,
+        expo: i32,
+    ) -> Result<()> {
+        let manual_price = &mut ctx.accounts.manual_price;
+
+        manual_price.price = price;
+        manual_price.conf = conf;
+        manual_price.expo = expo;
+        manual_price.publish_time = (Clock::get()?.unix_timestamp * 1000) as u64;
+
+        Ok(())
+    }
+
+    pub fn init_or_update_tranche(
+        ctx: Context<InitOrUpdateTranche>,
+        vault_count: u64,
+        index: u8,
+        params: InitOrUpdateTrancheParam,
+    ) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        let tranche = &mut ctx.accounts.tranche;
+
+        // TRANCHES ARE FIXED ONCE FUNDS HAVE BEEN COLLECTED
+        if vault.amount_collected != 0 {
+            return err!(ErrorCode::NotUpdatable);
+        }
+        if vault.start_date <= (Clock::get()?.unix_timestamp * 1000) as u64 {
+            return err!(ErrorCode::InvalidStartTimeInput);
+        }
+
+        // TRANCHES ARE CREATED IN ORDER OF SENIORITY
+        if index > vault.tranche_count || index >= MAX_TRANCHES {
+            return err!(ErrorCode::InvalidTranche);
+        }
+        if vault.coupon_interval != 0
+            || vault.amortization != AmortizationStyle::Bullet
+            || vault.require_yield_reserve
+            || vault.oversubscription
+        {
+            return err!(ErrorCode::InvalidTranche);
+        }
+        if params.min_amount == 0 {
+            return err!(ErrorCode::InvalidMultiple);
+        }
+        if index == vault.tranche_count {
+            vault.tranche_count += 1;
+        }
+
+        // TRANCHE CAPACITIES ADD UP TO THE VAULT CAPACITY
+        match (vault.tranche_capacity - tranche.vault_capacity).checked_add(params.vault_capacity) {
+            Some(result) => vault.tranche_capacity = result,
+            None => return err!(ErrorCode::Overflow),
+        }
+
+        tranche.vault_count = vault_count;
+        tranche.index = index;
+        tranche.yield_bps = params.yield_bps;
+        tranche.vault_capacity = params.vault_capacity;
+        tranche.min_amount = params.min_amount;
+
         Ok(())
     }
 
@@ -114,25 +542,132 @@ pub mod elemental_vault {
         ctx: Context<InitOrDepositUser>,
         vault_count: u64,
         amount_to_transfer: u64,
-    ) -> Result<()> {
+        tranche_index: u8,
+        allowlist: Option<AllowlistProof>,
+        allow_partial_fill: bool,
+    ) -> Result<u64> {
         let owner = &mut ctx.accounts.owner;
         let source_ata = &mut ctx.accounts.source_ata;
         let base_mint = &ctx.accounts.base_mint;
         let vault = &mut ctx.accounts.vault;
         let user = &mut ctx.accounts.user;
+        let tranche = &mut ctx.accounts.tranche;
         let destination_ata = &mut ctx.accounts.destination_ata;
 
-        if (Clock::get()?.unix_timestamp * 1000) as u64 >= vault.start_date {
+        let now = (Clock::get()?.unix_timestamp * 1000) as u64;
+        if now < vault.deposit_open {
+            return err!(ErrorCode::DepositNotOpen);
+        }
+        if now >= state::Vault::deposit_close(vault) {
             return err!(ErrorCode::VaultClose);
         }
+        if vault.status != VaultStatus::Active {
+            return err!(ErrorCode::VaultNotActive);
+        }
+
+        // KYC VAULTS REQUIRE AN UNEXPIRED CREDENTIAL FROM THEIR ISSUER, STILL A REGISTERED ATTESTOR
+        let mut credential_level = None;
+        if vault.kyc_issuer != Pubkey::default() {
+            match &ctx.accounts.credential {
+                Some(credential)
+                    if credential.is_valid(now)
+                        && ctx.accounts.global.is_attestor(&credential.issuer) =>
+                {
+                    credential_level = Some(credential.level)
+                }
+                _ => return err!(ErrorCode::InvalidCredential),
+            }
+        }
+
+        // ALLOWLISTED VAULTS REQUIRE A PROOF FOR THE WALLET AND ITS DEPOSIT CAP
+        let mut leaf_cap = 0;
+        if vault.allowlist_root != [0; 32] {
+            let allowlist = match allowlist {
+                Some(allowlist) => allowlist,
+                None => return err!(ErrorCode::NotAllowlisted),
+            };
+            if !state::Vault::is_allowlisted(vault, &owner.key(), allowlist.cap, &allowlist.proof) {
+                return err!(ErrorCode::NotAllowlisted);
+            }
+            leaf_cap = allowlist.cap;
+        }
+
+        // DEPOSITS OPEN ONCE THE AUTHORITY HAS PREFUNDED THE MAXIMUM YIELD
+        if vault.require_yield_reserve
+            && vault.yield_reserve < state::Vault::yield_reserve_required(vault)
+        {
+            return err!(ErrorCode::YieldReserveNotFunded);
+        }
+
+        // DEPOSITS OPEN ONCE THE AUTHORITY HAS LOCKED THE REQUIRED COLLATERAL
+        let oracle = ctx
+            .accounts
+            .collateral_oracle
+            .as_ref()
+            .map(|oracle| oracle.to_account_info());
+        let price = Price::load_collateral_price(vault, oracle.as_ref(), now)?;
+        if !state::Vault::is_collateralized(vault, price.as_ref(), base_mint.decimals) {
+            return err!(ErrorCode::InsufficientCollateral);
+        }
 
-        if amount_to_transfer % vault.min_amount != 0 {
+        // TRANCHED VAULTS ROUTE DEPOSITS THROUGH A SINGLE TRANCHE PER USER
+        if (vault.tranche_count > 0) != tranche.is_some()
+            || (user.amount > 0 && user.tranche != tranche_index)
+            || (vault.tranche_count > 0 && vault.tranche_capacity != vault.vault_capacity)
+        {
+            return err!(ErrorCode::InvalidTranche);
+        }
+
+        let min_amount = match tranche {
+            Some(tranche) => tranche.min_amount,
+            None => vault.min_amount,
+        };
+        if amount_to_transfer % min_amount != 0 {
             return err!(ErrorCode::InvalidMultiple);
         }
 
-        if amount_to_transfer + vault.amount_collected > vault.vault_capacity {
+        // PARTIAL FILL TAKES THE LARGEST MIN AMOUNT MULTIPLE THAT STILL FITS
+        let user_cap = state::Vault::user_cap(vault, leaf_cap, credential_level);
+        let mut amount_to_transfer = amount_to_transfer;
+        if allow_partial_fill {
+            let mut room = match vault.oversubscription {
+                true => u64::MAX,
+                false => vault.vault_capacity.saturating_sub(vault.amount_collected),
+            };
+            if let Some(tranche) = tranche {
+                room = room.min(
+                    tranche
+                        .vault_capacity
+                        .saturating_sub(tranche.amount_collected),
+                );
+            }
+            if user_cap != 0 {
+                room = room.min(user_cap.saturating_sub(user.amount + user.waitlisted));
+            }
+            if amount_to_transfer > room {
+                amount_to_transfer = room - room % min_amount;
+            }
+            if amount_to_transfer == 0 {
+                return err!(ErrorCode::AmountExceedVaultCapacity);
+            }
+        }
+
+        // CUMULATIVE DEPOSIT OF THE WALLET, WAITLIST INCLUDED, IS CAPPED
+        if user_cap != 0 && user.amount + user.waitlisted + amount_to_transfer > user_cap {
+            return err!(ErrorCode::AmountExceedUserCap);
+        }
+
+        // OVERSUBSCRIBED VAULTS ACCEPT COMMITMENTS BEYOND CAPACITY
+        if !vault.oversubscription
+            && amount_to_transfer + vault.amount_collected > vault.vault_capacity
+        {
             return err!(ErrorCode::AmountExceedVaultCapacity);
         }
+        if let Some(tranche) = tranche {
+            if amount_to_transfer + tranche.amount_collected > tranche.vault_capacity {
+                return err!(ErrorCode::AmountExceedVaultCapacity);
+            }
+        }
         // TRANSNFER AMOUNT FROM VAULT TO AUTHORITY ATA
         let transfer_cpi_accounts = TransferChecked {
             from: source_ata.to_account_info(),
@@ -150,6 +685,24 @@ pub mod elemental_vault {
             Some(result) => vault.amount_collected = result,
             None => return err!(ErrorCode::Overflow),
         }
+        if vault.oversubscription {
+            vault.amount_committed = vault.amount_collected;
+        }
+        if let Some(tranche) = tranche {
+            match tranche.amount_collected.checked_add(amount_to_transfer) {
+                Some(result) => tranche.amount_collected = result,
+                None => return err!(ErrorCode::Overflow),
+            }
+            user.tranche = tranche_index;
+        }
+
+        // NEW USER ACCOUNT
+        if user.amount == 0 {
+            match vault.user_count.checked_add(1) {
+                Some(result) => vault.user_count = result,
+                None => return err!(ErrorCode::Overflow),
+            }
+        }
 
         user.vault_count = vault_count;
         user.owner = owner.key();
@@ -158,20 +711,329 @@ pub mod elemental_vault {
             None => return err!(ErrorCode::Overflow),
         }
 
+        Ok(amount_to_transfer)
+    }
+
+    pub fn finalize_allocation(ctx: Context<FinalizeAllocation>, _vault_count: u64) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+
+        if !vault.oversubscription || vault.allocation_finalized {
+            return err!(ErrorCode::NotUpdatable);
+        }
+        if state::Vault::deposit_close(vault) > (Clock::get()?.unix_timestamp * 1000) as u64 {
+            return err!(ErrorCode::VaultNotReady);
+        }
+
+        // EVERY USER CLAIMS THEIR EXCESS IF COMMITMENTS EXCEED CAPACITY
+        vault.allocation_finalized = true;
+        vault.users_pending = match vault.amount_committed > vault.vault_capacity {
+            true => vault.user_count,
+            false => 0,
+        };
+
         Ok(())
     }
 
-    pub fn authority_withdraw(
-        ctx: Context<AuthorityWithdraw>,
-        vault_count: u64,
-        amount: u64,
-    ) -> Result<()> {
-        let destination_ata = &mut ctx.accounts.destination_ata;
+    pub fn claim_excess(ctx: Context<ClaimExcess>, vault_count: u64) -> Result<()> {
         let base_mint = &ctx.accounts.base_mint;
         let vault = &mut ctx.accounts.vault;
-        let vault_ata = &mut ctx.accounts.vault_ata;
+        let user = &mut ctx.accounts.user;
 
-        let signer_seed: &[&[&[u8]]] = &[&[
+        if !vault.allocation_finalized {
+            return err!(ErrorCode::AllocationPending);
+        }
+        if state::Vault::is_allocated(user, vault) {
+            return err!(ErrorCode::NothingToClaim);
+        }
+
+        // PRO-RATA ALLOCATION OF THE CAPACITY, THE REST IS REFUNDED
+        let allocation = state::Vault::calculate_allocation(user, vault);
+        let excess = user.amount - allocation;
+
+        let signer_seed: &[&[&[u8]]] = &[&[
+            b"vault".as_ref(),
+            &vault_count.to_le_bytes(),
+            &[ctx.bumps.vault],
+        ]];
+
+        // TRANSFER EXCESS FROM VAULT TO OWNER ATA
+        if excess > 0 {
+            let transfer_cpi_accounts = TransferChecked {
+                from: ctx.accounts.source_ata.to_account_info(),
+                mint: base_mint.to_account_info(),
+                to: ctx.accounts.destination_ata.to_account_info(),
+                authority: vault.to_account_info(),
+            };
+            let transfer_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                transfer_cpi_accounts,
+            )
+            .with_signer(signer_seed);
+            transfer_checked(transfer_ctx, excess, base_mint.decimals)?;
+        }
+
+        vault.amount_collected -= excess;
+        vault.users_pending -= 1;
+        user.amount = allocation;
+        user.allocated = true;
+
+        // CLOSE USER AND RETURN RENT IF NOTHING WAS ALLOCATED
+        if allocation == 0 {
+            vault.user_count -= 1;
+            user.close(ctx.accounts.owner.to_account_info())?;
+        }
+
+        Ok(())
+    }
+
+    pub fn cancel_deposit(ctx: Context<CancelDeposit>, vault_count: u64) -> Result<()> {
+        let base_mint = &ctx.accounts.base_mint;
+        let vault = &mut ctx.accounts.vault;
+        let user = &mut ctx.accounts.user;
+        let tranche = &mut ctx.accounts.tranche;
+
+        // DEPOSITS CAN BE CANCELLED UNTIL THE DEPOSIT WINDOW CLOSES
+        if (Clock::get()?.unix_timestamp * 1000) as u64 >= state::Vault::deposit_close(vault) {
+            return err!(ErrorCode::VaultClose);
+        }
+        if vault.status != VaultStatus::Active {
+            return err!(ErrorCode::VaultNotActive);
+        }
+        if (vault.tranche_count > 0) != tranche.is_some() {
+            return err!(ErrorCode::InvalidTranche);
+        }
+        if user.amount == 0 {
+            return err!(ErrorCode::NothingToClaim);
+        }
+
+        let signer_seed: &[&[&[u8]]] = &[&[
+            b"vault".as_ref(),
+            &vault_count.to_le_bytes(),
+            &[ctx.bumps.vault],
+        ]];
+
+        // TRANSFER DEPOSIT FROM VAULT BACK TO USER ATA
+        let transfer_cpi_accounts = TransferChecked {
+            from: ctx.accounts.source_ata.to_account_info(),
+            mint: base_mint.to_account_info(),
+            to: ctx.accounts.destination_ata.to_account_info(),
+            authority: vault.to_account_info(),
+        };
+        let transfer_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            transfer_cpi_accounts,
+        )
+        .with_signer(signer_seed);
+        transfer_checked(transfer_ctx, user.amount, base_mint.decimals)?;
+
+        vault.amount_collected -= user.amount;
+        if vault.oversubscription {
+            vault.amount_committed = vault.amount_collected;
+        }
+        if let Some(tranche) = tranche {
+            tranche.amount_collected -= user.amount;
+        }
+        vault.user_count -= 1;
+        user.amount = 0;
+
+        // FREED CAPACITY IS TAKEN BY THE HEAD OF THE WAITLIST WHEN IT FITS
+        if let (Some(waitlist_entry), Some(waitlist_owner), Some(waitlist_user)) = (
+            &mut ctx.accounts.waitlist_entry,
+            &ctx.accounts.waitlist_owner,
+            &mut ctx.accounts.waitlist_user,
+        ) {
+            if waitlist_entry.owner != waitlist_owner.key()
+                || waitlist_user.owner != waitlist_owner.key()
+                || waitlist_user.vault_count != vault_count
+            {
+                return err!(ErrorCode::Unauthorized);
+            }
+            if waitlist_owner.key() != ctx.accounts.owner.key()
+                && waitlist_entry.amount + vault.amount_collected <= vault.vault_capacity
+            {
+                promote_waitlist_entry(vault, waitlist_entry, waitlist_user)?;
+                waitlist_entry.close(waitlist_owner.to_account_info())?;
+            }
+        }
+
+        // CLOSE USER AND RETURN RENT UNLESS DEPOSITS ARE STILL WAITLISTED
+        if user.waitlisted == 0 {
+            user.close(ctx.accounts.owner.to_account_info())?;
+        }
+
+        Ok(())
+    }
+
+    pub fn join_waitlist(ctx: Context<JoinWaitlist>, vault_count: u64, amount: u64) -> Result<()> {
+        let base_mint = &ctx.accounts.base_mint;
+        let vault = &mut ctx.accounts.vault;
+        let waitlist_entry = &mut ctx.accounts.waitlist_entry;
+        let user = &mut ctx.accounts.user;
+
+        let now = (Clock::get()?.unix_timestamp * 1000) as u64;
+        if now < vault.deposit_open {
+            return err!(ErrorCode::DepositNotOpen);
+        }
+        if now >= state::Vault::deposit_close(vault) {
+            return err!(ErrorCode::VaultClose);
+        }
+        if vault.status != VaultStatus::Active || !state::Vault::has_waitlist(vault) {
+            return err!(ErrorCode::WaitlistUnavailable);
+        }
+        match amount.checked_rem(vault.min_amount) {
+            Some(0) if amount > 0 => {}
+            _ => return err!(ErrorCode::InvalidMultiple),
+        }
+        // CUMULATIVE DEPOSIT OF THE WALLET, WAITLIST INCLUDED, IS CAPPED
+        if vault.max_per_user != 0 && user.amount + user.waitlisted + amount > vault.max_per_user {
+            return err!(ErrorCode::AmountExceedUserCap);
+        }
+
+        // ONLY ONCE THE DEPOSIT NO LONGER FITS IN THE VAULT
+        if amount + vault.amount_collected <= vault.vault_capacity {
+            return err!(ErrorCode::VaultNotFull);
+        }
+
+        // TRANSFER DEPOSIT FROM USER TO VAULT ATA IN ESCROW
+        let transfer_cpi_accounts = TransferChecked {
+            from: ctx.accounts.source_ata.to_account_info(),
+            mint: base_mint.to_account_info(),
+            to: ctx.accounts.vault_ata.to_account_info(),
+            authority: ctx.accounts.owner.to_account_info(),
+        };
+        let transfer_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            transfer_cpi_accounts,
+        );
+        transfer_checked(transfer_ctx, amount, base_mint.decimals)?;
+
+        waitlist_entry.vault_count = vault_count;
+        waitlist_entry.entry_id = vault.waitlist_count;
+        waitlist_entry.owner = ctx.accounts.owner.key();
+        waitlist_entry.amount = amount;
+
+        match vault.waitlist_amount.checked_add(amount) {
+            Some(result) => vault.waitlist_amount = result,
+            None => return err!(ErrorCode::Overflow),
+        }
+        vault.waitlist_count += 1;
+
+        // USER ACCOUNT IS FUNDED BY THE OWNER SO PROMOTION NEEDS NO RENT
+        user.vault_count = vault_count;
+        user.owner = ctx.accounts.owner.key();
+        match user.waitlisted.checked_add(amount) {
+            Some(result) => user.waitlisted = result,
+            None => return err!(ErrorCode::Overflow),
+        }
+
+        Ok(())
+    }
+
+    pub fn promote_waitlist(ctx: Context<PromoteWaitlist>, _vault_count: u64) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        let waitlist_entry = &mut ctx.accounts.waitlist_entry;
+        let user = &mut ctx.accounts.user;
+
+        if (Clock::get()?.unix_timestamp * 1000) as u64 >= state::Vault::deposit_close(vault) {
+            return err!(ErrorCode::VaultClose);
+        }
+        if vault.status != VaultStatus::Active {
+            return err!(ErrorCode::VaultNotActive);
+        }
+
+        // HEAD OF THE WAITLIST MOVES IN ONCE ITS WHOLE DEPOSIT FITS
+        if waitlist_entry.amount + vault.amount_collected > vault.vault_capacity {
+            return err!(ErrorCode::AmountExceedVaultCapacity);
+        }
+
+        promote_waitlist_entry(vault, waitlist_entry, user)
+    }
+
+    pub fn refund_waitlist(
+        ctx: Context<RefundWaitlist>,
+        vault_count: u64,
+        _entry_id: u64,
+    ) -> Result<()> {
+        let base_mint = &ctx.accounts.base_mint;
+        let vault = &mut ctx.accounts.vault;
+        let waitlist_entry = &mut ctx.accounts.waitlist_entry;
+        let amount = waitlist_entry.amount;
+
+        // OWNER CAN LEAVE THE WAITLIST ANY TIME, ANYONE CAN REFUND ONCE DEPOSITS CLOSE
+        let deposits_open =
+            state::Vault::deposit_close(vault) > (Clock::get()?.unix_timestamp * 1000) as u64;
+        if deposits_open && ctx.accounts.payer.key() != ctx.accounts.owner.key() {
+            return err!(ErrorCode::Unauthorized);
+        }
+        if deposits_open && ctx.accounts.user.is_none() {
+            return err!(ErrorCode::WaitlistOutstanding);
+        }
+
+        let signer_seed: &[&[&[u8]]] = &[&[
+            b"vault".as_ref(),
+            &vault_count.to_le_bytes(),
+            &[ctx.bumps.vault],
+        ]];
+
+        // TRANSFER ESCROWED DEPOSIT FROM VAULT BACK TO OWNER ATA
+        if amount > 0 {
+            let transfer_cpi_accounts = TransferChecked {
+                from: ctx.accounts.vault_ata.to_account_info(),
+                mint: base_mint.to_account_info(),
+                to: ctx.accounts.destination_ata.to_account_info(),
+                authority: vault.to_account_info(),
+            };
+            let transfer_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                transfer_cpi_accounts,
+            )
+            .with_signer(signer_seed);
+            transfer_checked(transfer_ctx, amount, base_mint.decimals)?;
+        }
+
+        vault.waitlist_amount -= amount;
+        waitlist_entry.amount = 0;
+
+        // CLOSE USER AND RETURN RENT WHEN NOTHING IS LEFT IN THE VAULT
+        if let Some(user) = &mut ctx.accounts.user {
+            user.waitlisted -= amount;
+            if user.amount == 0 && user.waitlisted == 0 {
+                user.close(ctx.accounts.owner.to_account_info())?;
+            }
+        }
+
+        // EMPTIED ENTRIES STAY IN THE QUEUE UNTIL PROMOTION SKIPS THEM
+        if !deposits_open {
+            waitlist_entry.close(ctx.accounts.owner.to_account_info())?;
+        }
+
+        Ok(())
+    }
+
+    pub fn authority_withdraw(
+        ctx: Context<AuthorityWithdraw>,
+        vault_count: u64,
+        amount: u64,
+    ) -> Result<()> {
+        let destination_ata = &mut ctx.accounts.destination_ata;
+        let base_mint = &ctx.accounts.base_mint;
+        let vault = &mut ctx.accounts.vault;
+        let vault_ata = &mut ctx.accounts.vault_ata;
+
+        // RESERVED BALANCE BELONGS TO QUEUED WITHDRAWALS, THE YIELD RESERVE, SETTLED PAYOUTS,
+        // UNCLAIMED INSTALMENTS AND EXCESS COMMITMENTS OR WAITLIST DEPOSITS NOT YET REFUNDED
+        let now = (Clock::get()?.unix_timestamp * 1000) as u64;
+        let reserved = vault.reserved_balance()
+            + state::Vault::locked_yield_reserve(vault, now)
+            + vault.settled_reserve()
+            + state::Vault::pending_excess(vault)
+            + state::Vault::unclaimed_instalments(vault)
+            + vault.waitlist_amount;
+        if amount > vault_ata.amount.saturating_sub(reserved) {
+            return err!(ErrorCode::InsufficientLiquidity);
+        }
+
+        let signer_seed: &[&[&[u8]]] = &[&[
             b"vault".as_ref(),
             &vault_count.to_le_bytes(),
             &[ctx.bumps.vault],
@@ -200,17 +1062,59 @@ pub mod elemental_vault {
     }
 
     pub fn user_withdraw(ctx: Context<UserWithdraw>, vault_count: u64) -> Result<()> {
+        // OVERSUBSCRIBED COMMITMENTS MUST BE ALLOCATED FIRST
+        if !state::Vault::is_allocated(&ctx.accounts.user, &ctx.accounts.vault) {
+            return err!(ErrorCode::AllocationPending);
+        }
+
         let source_ata = &mut ctx.accounts.source_ata;
         let base_mint = &ctx.accounts.base_mint;
         let vault = &mut ctx.accounts.vault;
         let user = &mut ctx.accounts.user;
+        let tranche = &mut ctx.accounts.tranche;
         let destination_ata = &mut ctx.accounts.destination_ata;
+        let now = (Clock::get()?.unix_timestamp * 1000) as u64;
+
+        if vault.amortization != AmortizationStyle::Bullet {
+            return err!(ErrorCode::InvalidAmortization);
+        }
+        if (vault.tranche_count > 0) != tranche.is_some() {
+            return err!(ErrorCode::InvalidTranche);
+        }
 
-        msg!("unix {}", Clock::get()?.unix_timestamp * 1000);
+        msg!("unix {}", now);
         msg!("end_date {}", vault.end_date);
-        if (Clock::get()?.unix_timestamp * 1000) as u64 <= vault.end_date {
+        if now <= vault.end_date {
             return err!(ErrorCode::VaultNotReady);
         }
+        if user.is_vote_locked(now) {
+            return err!(ErrorCode::VoteLocked);
+        }
+
+        // FIRST-LOSS CAPITAL MUST BE COUNTED WHENEVER THE VAULT HOLDS ANY
+        let first_loss_amount = match &ctx.accounts.first_loss_ata {
+            Some(first_loss_ata) => first_loss_ata.amount,
+            None if vault.first_loss_amount > 0 => return err!(ErrorCode::FirstLossRequired),
+            None => 0,
+        };
+
+        // SETTLE ON FIRST WITHDRAWAL ONCE THE VAULT HOLDS EVERYTHING OWED
+        if vault.status == VaultStatus::Active || vault.status == VaultStatus::Liquidating {
+            if vault.tranche_count > 0 {
+                return err!(ErrorCode::VaultNotSettled);
+            }
+            if state::Vault::allocation_pending(vault) {
+                return err!(ErrorCode::AllocationPending);
+            }
+            if state::Vault::extension_pending(vault) {
+                return err!(ErrorCode::ExtensionPending);
+            }
+            let assets = source_ata.amount - vault.waitlist_amount + first_loss_amount;
+            vault.settle(assets, std::iter::empty(), now);
+            if vault.status == VaultStatus::Defaulted {
+                return err!(ErrorCode::VaultNotReady);
+            }
+        }
 
         let signer_seed: &[&[&[u8]]] = &[&[
             b"vault".as_ref(),
@@ -218,9 +1122,125 @@ pub mod elemental_vault {
             &[ctx.bumps.vault],
         ]];
 
-        let amount_to_transfer = state::Vault::calculate_payout(&user.amount, vault);
+        // COUPONS ALREADY CLAIMED ARE DEDUCTED FROM THE FINAL PAYOUT
+        let payout = state::Vault::calculate_user_payout(user, vault, tranche.as_deref());
+
+        // ONLY THE VESTED PORTION NOT YET CLAIMED IS PAID
+        let vested = state::Vault::calculate_vested(payout, vault, now);
+        let amount_to_transfer = vested - user.amount_claimed;
+        if amount_to_transfer == 0 {
+            return err!(ErrorCode::NothingToClaim);
+        }
+
+        // FIRST-LOSS CAPITAL TOPS UP ANY SHORTFALL IN THE VAULT ATA
+        let amount_from_vault = amount_to_transfer.min(source_ata.amount - vault.waitlist_amount);
+        let amount_from_first_loss = amount_to_transfer - amount_from_vault;
+        if amount_from_first_loss > first_loss_amount {
+            return err!(ErrorCode::VaultNotReady);
+        }
 
         // TRANSNFER AMOUNT FROM VAULT TO AUTHORITY ATA
+        if amount_from_vault > 0 {
+            let transfer_cpi_accounts = TransferChecked {
+                from: source_ata.to_account_info(),
+                mint: base_mint.to_account_info(),
+                to: destination_ata.to_account_info(),
+                authority: vault.to_account_info(),
+            };
+            let transfer_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                transfer_cpi_accounts,
+            )
+            .with_signer(signer_seed);
+            transfer_checked(transfer_ctx, amount_from_vault, base_mint.decimals)?;
+        }
+        if let Some(first_loss_ata) = &ctx.accounts.first_loss_ata {
+            if amount_from_first_loss > 0 {
+                let transfer_cpi_accounts = TransferChecked {
+                    from: first_loss_ata.to_account_info(),
+                    mint: base_mint.to_account_info(),
+                    to: destination_ata.to_account_info(),
+                    authority: vault.to_account_info(),
+                };
+                let transfer_ctx = CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    transfer_cpi_accounts,
+                )
+                .with_signer(signer_seed);
+                transfer_checked(transfer_ctx, amount_from_first_loss, base_mint.decimals)?;
+
+                vault.first_loss_amount -= amount_from_first_loss;
+                match vault.first_loss_used.checked_add(amount_from_first_loss) {
+                    Some(result) => vault.first_loss_used = result,
+                    None => return err!(ErrorCode::Overflow),
+                }
+            }
+        }
+
+        match vault.amount_redeemed.checked_add(amount_to_transfer) {
+            Some(result) => vault.amount_redeemed = result,
+            None => return err!(ErrorCode::Overflow),
+        }
+        if user.opted_out {
+            match vault.opt_out_paid.checked_add(amount_to_transfer) {
+                Some(result) => vault.opt_out_paid = result,
+                None => return err!(ErrorCode::Overflow),
+            }
+        }
+        if let Some(tranche) = tranche {
+            match tranche.amount_redeemed.checked_add(amount_to_transfer) {
+                Some(result) => tranche.amount_redeemed = result,
+                None => return err!(ErrorCode::Overflow),
+            }
+        }
+        user.amount_claimed = vested;
+
+        // CLOSE USER AND RETURN RENT ONCE FULLY CLAIMED, KEEPING IT FOR A COLLATERAL CLAIM
+        if user.amount_claimed == payout && !state::Vault::collateral_outstanding(user, vault) {
+            vault.user_count -= 1;
+            user.close(ctx.accounts.owner.to_account_info())?;
+        }
+
+        Ok(())
+    }
+
+    pub fn claim_coupon(ctx: Context<UserWithdraw>, vault_count: u64) -> Result<()> {
+        // OVERSUBSCRIBED COMMITMENTS MUST ALL BE ALLOCATED FIRST
+        if state::Vault::allocation_pending(&ctx.accounts.vault) {
+            return err!(ErrorCode::AllocationPending);
+        }
+
+        let source_ata = &mut ctx.accounts.source_ata;
+        let base_mint = &ctx.accounts.base_mint;
+        let vault = &mut ctx.accounts.vault;
+        let user = &mut ctx.accounts.user;
+        let destination_ata = &mut ctx.accounts.destination_ata;
+        let now = (Clock::get()?.unix_timestamp * 1000) as u64;
+
+        // REMAINING COUPONS ARE PAID BY USER_WITHDRAW AFTER END DATE
+        if now > vault.end_date {
+            return err!(ErrorCode::CouponClosed);
+        }
+
+        let periods = state::Vault::coupon_periods_elapsed(vault, now);
+        if periods <= user.last_coupon_period {
+            return err!(ErrorCode::NothingToClaim);
+        }
+        let amount_to_transfer = state::Vault::calculate_coupons(&user.amount, vault, periods)
+            - state::Vault::calculate_coupons(&user.amount, vault, user.last_coupon_period);
+
+        // WAITLIST DEPOSITS IN ESCROW ARE NOT AVAILABLE FOR PAYOUTS
+        if source_ata.amount - vault.waitlist_amount < amount_to_transfer {
+            return err!(ErrorCode::InsufficientLiquidity);
+        }
+
+        let signer_seed: &[&[&[u8]]] = &[&[
+            b"vault".as_ref(),
+            &vault_count.to_le_bytes(),
+            &[ctx.bumps.vault],
+        ]];
+
+        // TRANSFER COUPON FROM VAULT TO USER
         let transfer_cpi_accounts = TransferChecked {
             from: source_ata.to_account_info(),
             mint: base_mint.to_account_info(),
@@ -234,64 +1254,1900 @@ pub mod elemental_vault {
         .with_signer(signer_seed);
         transfer_checked(transfer_ctx, amount_to_transfer, base_mint.decimals)?;
 
+        match vault.coupons_paid.checked_add(amount_to_transfer) {
+            Some(result) => vault.coupons_paid = result,
+            None => return err!(ErrorCode::Overflow),
+        }
+        match user.coupons_claimed.checked_add(amount_to_transfer) {
+            Some(result) => user.coupons_claimed = result,
+            None => return err!(ErrorCode::Overflow),
+        }
         match vault.amount_redeemed.checked_add(amount_to_transfer) {
             Some(result) => vault.amount_redeemed = result,
             None => return err!(ErrorCode::Overflow),
         }
+        user.last_coupon_period = periods;
 
         Ok(())
     }
 
-    pub fn close_vault(
-        ctx: Context<CloseVault>,
-        vault_count: u64,
-        _authority: Pubkey,
-    ) -> Result<()> {
-        let vault = &ctx.accounts.vault;
-        let source_ata = &ctx.accounts.source_ata;
-        let destination_ata = &ctx.accounts.destination_ata;
+    pub fn fund_yield_reserve(ctx: Context<Repay>, _vault_count: u64, amount: u64) -> Result<()> {
         let base_mint = &ctx.accounts.base_mint;
+        let vault = &mut ctx.accounts.vault;
 
-        // PDA REQUIRE SEED SIGNER
-        let signer_seed: &[&[&[u8]]] = &[&[
-            b"vault".as_ref(),
-            &vault_count.to_le_bytes(),
-            &[ctx.bumps.vault],
-        ]];
-
-        if vault.end_date + vault.withdraw_timeframe > (Clock::get()?.unix_timestamp * 1000) as u64
-            && source_ata.amount > 0
-        {
-            return err!(ErrorCode::VaultNotReady);
+        if !vault.require_yield_reserve || vault.status != VaultStatus::Active {
+            return err!(ErrorCode::NotUpdatable);
         }
 
-        // TRANSNFER ALL FUNDS IN VAULT TO AUTHORITY
+        // TRANSFER RESERVE FROM AUTHORITY TO VAULT ATA
         let transfer_cpi_accounts = TransferChecked {
-            from: source_ata.to_account_info(),
+            from: ctx.accounts.source_ata.to_account_info(),
             mint: base_mint.to_account_info(),
-            to: destination_ata.to_account_info(),
-            authority: vault.to_account_info(),
+            to: ctx.accounts.vault_ata.to_account_info(),
+            authority: ctx.accounts.authority.to_account_info(),
         };
         let transfer_ctx = CpiContext::new(
             ctx.accounts.token_program.to_account_info(),
             transfer_cpi_accounts,
-        )
-        .with_signer(signer_seed);
-        transfer_checked(transfer_ctx, source_ata.amount, base_mint.decimals)?;
+        );
+        transfer_checked(transfer_ctx, amount, base_mint.decimals)?;
 
-        // CLOSE VAULT AND TRANSFER RENT TO CREATOR
-        let close_cpi_accounts = CloseAccount {
-            account: source_ata.to_account_info(),
-            destination: ctx.accounts.creator.to_account_info(),
-            authority: vault.to_account_info(),
-        };
-        let close_ctx = CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            close_cpi_accounts,
-        )
-        .with_signer(signer_seed);
-        close_account(close_ctx)?;
+        match vault.yield_reserve.checked_add(amount) {
+            Some(result) => vault.yield_reserve = result,
+            None => return err!(ErrorCode::Overflow),
+        }
 
         Ok(())
     }
+
+    pub fn repay_instalment(ctx: Context<Repay>, _vault_count: u64) -> Result<()> {
+        let base_mint = &ctx.accounts.base_mint;
+        let vault = &mut ctx.accounts.vault;
+
+        if vault.amortization == AmortizationStyle::Bullet {
+            return err!(ErrorCode::InvalidAmortization);
+        }
+        if (Clock::get()?.unix_timestamp * 1000) as u64 <= vault.start_date {
+            return err!(ErrorCode::VaultNotActive);
+        }
+        if vault.instalments_repaid == vault.instalment_count {
+            return err!(ErrorCode::InstalmentsRepaid);
+        }
+        // INSTALMENTS ARE SIZED ON THE PRINCIPAL LEFT ONCE EVERY EXCESS IS REFUNDED
+        if state::Vault::allocation_pending(vault) {
+            return err!(ErrorCode::AllocationPending);
+        }
+
+        let amount = match state::Vault::calculate_instalments(
+            vault.amount_collected,
+            vault,
+            vault.instalments_repaid,
+            vault.instalments_repaid + 1,
+        ) {
+            Some(result) => result,
+            None => return err!(ErrorCode::Overflow),
+        };
+
+        // TRANSFER INSTALMENT FROM AUTHORITY TO VAULT ATA
+        let transfer_cpi_accounts = TransferChecked {
+            from: ctx.accounts.source_ata.to_account_info(),
+            mint: base_mint.to_account_info(),
+            to: ctx.accounts.vault_ata.to_account_info(),
+            authority: ctx.accounts.authority.to_account_info(),
+        };
+        let transfer_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            transfer_cpi_accounts,
+        );
+        transfer_checked(transfer_ctx, amount, base_mint.decimals)?;
+
+        match vault.amount_repaid.checked_add(amount) {
+            Some(result) => vault.amount_repaid = result,
+            None => return err!(ErrorCode::Overflow),
+        }
+        vault.instalments_repaid += 1;
+
+        // FINAL INSTALMENT SETTLES THE VAULT, RESERVING WHAT USERS HAVE YET TO CLAIM
+        if vault.instalments_repaid == vault.instalment_count {
+            let unclaimed = state::Vault::unclaimed_instalments(vault);
+            vault.settled_owed = unclaimed;
+            vault.settled_recovered = unclaimed;
+            vault.redeemed_at_settlement = vault.amount_redeemed;
+            vault.status = VaultStatus::Settled;
+        }
+
+        Ok(())
+    }
+
+    pub fn claim_instalment(ctx: Context<UserWithdraw>, vault_count: u64) -> Result<()> {
+        // OVERSUBSCRIBED COMMITMENTS MUST BE ALLOCATED FIRST
+        if !state::Vault::is_allocated(&ctx.accounts.user, &ctx.accounts.vault) {
+            return err!(ErrorCode::AllocationPending);
+        }
+
+        let source_ata = &mut ctx.accounts.source_ata;
+        let base_mint = &ctx.accounts.base_mint;
+        let vault = &mut ctx.accounts.vault;
+        let user = &mut ctx.accounts.user;
+        let destination_ata = &mut ctx.accounts.destination_ata;
+
+        if vault.amortization == AmortizationStyle::Bullet {
+            return err!(ErrorCode::InvalidAmortization);
+        }
+        if user.instalments_claimed >= vault.instalments_repaid {
+            return err!(ErrorCode::NothingToClaim);
+        }
+
+        // USER RECEIVES PRO-RATA SHARE OF EVERY REPAID INSTALMENT
+        let amount_to_transfer = match state::Vault::calculate_user_instalments(user, vault) {
+            Some(result) => result,
+            None => return err!(ErrorCode::Overflow),
+        };
+        if source_ata.amount - vault.waitlist_amount < amount_to_transfer {
+            return err!(ErrorCode::InsufficientLiquidity);
+        }
+
+        let signer_seed: &[&[&[u8]]] = &[&[
+            b"vault".as_ref(),
+            &vault_count.to_le_bytes(),
+            &[ctx.bumps.vault],
+        ]];
+
+        // TRANSFER INSTALMENT SHARE FROM VAULT TO USER
+        let transfer_cpi_accounts = TransferChecked {
+            from: source_ata.to_account_info(),
+            mint: base_mint.to_account_info(),
+            to: destination_ata.to_account_info(),
+            authority: vault.to_account_info(),
+        };
+        let transfer_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            transfer_cpi_accounts,
+        )
+        .with_signer(signer_seed);
+        transfer_checked(transfer_ctx, amount_to_transfer, base_mint.decimals)?;
+
+        match vault.amount_redeemed.checked_add(amount_to_transfer) {
+            Some(result) => vault.amount_redeemed = result,
+            None => return err!(ErrorCode::Overflow),
+        }
+        match user.amount_claimed.checked_add(amount_to_transfer) {
+            Some(result) => user.amount_claimed = result,
+            None => return err!(ErrorCode::Overflow),
+        }
+        user.instalments_claimed = vault.instalments_repaid;
+
+        // CLOSE USER AND RETURN RENT AFTER FINAL INSTALMENT
+        if user.instalments_claimed == vault.instalment_count {
+            vault.user_count -= 1;
+            user.close(ctx.accounts.owner.to_account_info())?;
+        }
+
+        Ok(())
+    }
+
+    pub fn early_withdraw(ctx: Context<UserWithdraw>, vault_count: u64) -> Result<()> {
+        // OVERSUBSCRIBED COMMITMENTS MUST BE ALLOCATED FIRST
+        if !state::Vault::is_allocated(&ctx.accounts.user, &ctx.accounts.vault) {
+            return err!(ErrorCode::AllocationPending);
+        }
+
+        let source_ata = &mut ctx.accounts.source_ata;
+        let base_mint = &ctx.accounts.base_mint;
+        let vault = &mut ctx.accounts.vault;
+        let user = &mut ctx.accounts.user;
+        let tranche = &mut ctx.accounts.tranche;
+        let destination_ata = &mut ctx.accounts.destination_ata;
+        let now = (Clock::get()?.unix_timestamp * 1000) as u64;
+
+        if !vault.early_withdraw || user.opted_out {
+            return err!(ErrorCode::EarlyWithdrawDisabled);
+        }
+        if user.is_vote_locked(now) {
+            return err!(ErrorCode::VoteLocked);
+        }
+        if (vault.tranche_count > 0) != tranche.is_some() {
+            return err!(ErrorCode::InvalidTranche);
+        }
+
+        // ONLY BETWEEN START DATE AND END DATE, NOT WHILE THE VAULT IS BEING LIQUIDATED
+        if now < vault.start_date || now > vault.end_date || vault.status != VaultStatus::Active {
+            return err!(ErrorCode::VaultNotActive);
+        }
+
+        let penalty = state::Vault::calculate_early_penalty(&user.amount, vault, now);
+        let amount_to_transfer = user.amount - penalty;
+
+        // WAITLIST DEPOSITS IN ESCROW ARE NOT AVAILABLE FOR PAYOUTS
+        if source_ata.amount - vault.waitlist_amount < amount_to_transfer {
+            return err!(ErrorCode::InsufficientLiquidity);
+        }
+
+        let signer_seed: &[&[&[u8]]] = &[&[
+            b"vault".as_ref(),
+            &vault_count.to_le_bytes(),
+            &[ctx.bumps.vault],
+        ]];
+
+        // TRANSFER PRINCIPAL LESS PENALTY FROM VAULT TO USER
+        let transfer_cpi_accounts = TransferChecked {
+            from: source_ata.to_account_info(),
+            mint: base_mint.to_account_info(),
+            to: destination_ata.to_account_info(),
+            authority: vault.to_account_info(),
+        };
+        let transfer_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            transfer_cpi_accounts,
+        )
+        .with_signer(signer_seed);
+        transfer_checked(transfer_ctx, amount_to_transfer, base_mint.decimals)?;
+
+        match vault.amount_early_withdrawn.checked_add(user.amount) {
+            Some(result) => vault.amount_early_withdrawn = result,
+            None => return err!(ErrorCode::Overflow),
+        }
+        match vault.penalty_collected.checked_add(penalty) {
+            Some(result) => vault.penalty_collected = result,
+            None => return err!(ErrorCode::Overflow),
+        }
+        // COUPONS ALREADY PAID NO LONGER COUNT AGAINST WHAT THE REMAINING USERS ARE OWED
+        match vault.coupons_released.checked_add(user.coupons_claimed) {
+            Some(result) => vault.coupons_released = result,
+            None => return err!(ErrorCode::Overflow),
+        }
+        match vault.amount_redeemed.checked_add(amount_to_transfer) {
+            Some(result) => vault.amount_redeemed = result,
+            None => return err!(ErrorCode::Overflow),
+        }
+        if let Some(tranche) = tranche {
+            match tranche.amount_early_withdrawn.checked_add(user.amount) {
+                Some(result) => tranche.amount_early_withdrawn = result,
+                None => return err!(ErrorCode::Overflow),
+            }
+            match tranche.penalty_collected.checked_add(penalty) {
+                Some(result) => tranche.penalty_collected = result,
+                None => return err!(ErrorCode::Overflow),
+            }
+            match tranche.amount_redeemed.checked_add(amount_to_transfer) {
+                Some(result) => tranche.amount_redeemed = result,
+                None => return err!(ErrorCode::Overflow),
+            }
+        }
+
+        // SHARE PENALTY WITH DEPOSITORS ON THE CURRENT TERMS, OTHERWISE IT STAYS WITH AUTHORITY
+        let remaining_amount = state::Vault::extended_principal(vault);
+        if vault.penalty_recipient == PenaltyRecipient::Depositors && remaining_amount > 0 {
+            let penalty_per_amount = (penalty as u128) * PENALTY_SCALE / (remaining_amount as u128);
+            match vault.penalty_per_amount.checked_add(penalty_per_amount) {
+                Some(result) => vault.penalty_per_amount = result,
+                None => return err!(ErrorCode::Overflow),
+            }
+        }
+
+        // CLOSE USER AND RETURN RENT
+        vault.user_count -= 1;
+        user.close(ctx.accounts.owner.to_account_info())?;
+
+        Ok(())
+    }
+
+    pub fn propose_extension(
+        ctx: Context<ProposeExtension>,
+        _vault_count: u64,
+        end_date: u64,
+        yield_bps: u16,
+        opt_out_deadline: u64,
+    ) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        let now = (Clock::get()?.unix_timestamp * 1000) as u64;
+
+        if vault.status != VaultStatus::Active {
+            return err!(ErrorCode::VaultNotActive);
+        }
+
+        // A VAULT CAN BE EXTENDED ONCE, ONLY FOR BULLET REPAYMENT WITHOUT COUPONS, VESTING OR TRANCHES
+        if vault.original_end_date != 0
+            || state::Vault::extension_pending(vault)
+            || vault.tranche_count > 0
+            || vault.coupon_interval != 0
+            || vault.vesting_period != 0
+            || vault.amortization != AmortizationStyle::Bullet
+            || state::Vault::allocation_pending(vault)
+        {
+            return err!(ErrorCode::InvalidExtension);
+        }
+
+        // DEPOSITS MUST HAVE CLOSED SO EVERY USER CAN DECIDE ON THE SAME TERMS
+        if now < state::Vault::deposit_close(vault) || now >= vault.end_date {
+            return err!(ErrorCode::InvalidExtension);
+        }
+        if end_date <= vault.end_date {
+            return err!(ErrorCode::InvalidEndTimeInput);
+        }
+
+        // USERS MUST BE ABLE TO OPT OUT BEFORE THE ORIGINAL END DATE
+        if opt_out_deadline <= now || opt_out_deadline > vault.end_date {
+            return err!(ErrorCode::InvalidTimeInput);
+        }
+
+        vault.extension_end_date = end_date;
+        vault.extension_yield_bps = yield_bps;
+        vault.opt_out_deadline = opt_out_deadline;
+
+        Ok(())
+    }
+
+    pub fn opt_out_extension(ctx: Context<OptOutExtension>, _vault_count: u64) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        let user = &mut ctx.accounts.user;
+
+        if !state::Vault::extension_pending(vault) || user.opted_out {
+            return err!(ErrorCode::InvalidExtension);
+        }
+        if (Clock::get()?.unix_timestamp * 1000) as u64 >= vault.opt_out_deadline {
+            return err!(ErrorCode::InvalidExtension);
+        }
+
+        // USER KEEPS THE ORIGINAL TERMS
+        user.opted_out = true;
+        match vault.amount_opted_out.checked_add(user.amount) {
+            Some(result) => vault.amount_opted_out = result,
+            None => return err!(ErrorCode::Overflow),
+        }
+
+        Ok(())
+    }
+
+    pub fn apply_extension(ctx: Context<ApplyExtension>, _vault_count: u64) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+
+        if !state::Vault::extension_pending(vault) {
+            return err!(ErrorCode::InvalidExtension);
+        }
+        if vault.status != VaultStatus::Active {
+            return err!(ErrorCode::VaultNotActive);
+        }
+        if vault.opt_out_deadline > (Clock::get()?.unix_timestamp * 1000) as u64 {
+            return err!(ErrorCode::VaultNotReady);
+        }
+
+        // ORIGINAL TERMS ARE KEPT FOR USERS WHO OPTED OUT
+        vault.original_end_date = vault.end_date;
+        vault.original_yield_bps = vault.yield_bps;
+        vault.end_date = vault.extension_end_date;
+        vault.yield_bps = vault.extension_yield_bps;
+        vault.repaid_at = 0;
+
+        vault.extension_end_date = 0;
+        vault.extension_yield_bps = 0;
+        vault.opt_out_deadline = 0;
+
+        Ok(())
+    }
+
+    pub fn redeem_opt_out(ctx: Context<UserWithdraw>, vault_count: u64) -> Result<()> {
+        let source_ata = &mut ctx.accounts.source_ata;
+        let base_mint = &ctx.accounts.base_mint;
+        let vault = &mut ctx.accounts.vault;
+        let user = &mut ctx.accounts.user;
+
+        if !user.opted_out || vault.original_end_date == 0 {
+            return err!(ErrorCode::InvalidExtension);
+        }
+
+        // OPTED-OUT USERS REDEEM FROM THE ORIGINAL END DATE, AFTER SETTLEMENT THROUGH USER_WITHDRAW
+        let now = (Clock::get()?.unix_timestamp * 1000) as u64;
+        if now <= vault.original_end_date {
+            return err!(ErrorCode::VaultNotReady);
+        }
+        if user.is_vote_locked(now) {
+            return err!(ErrorCode::VoteLocked);
+        }
+        if vault.status != VaultStatus::Active {
+            return err!(ErrorCode::VaultNotActive);
+        }
+
+        let amount_to_transfer = state::Vault::calculate_opt_out_payout(&user.amount, vault);
+        if source_ata.amount - vault.waitlist_amount < amount_to_transfer {
+            return err!(ErrorCode::InsufficientLiquidity);
+        }
+
+        let signer_seed: &[&[&[u8]]] = &[&[
+            b"vault".as_ref(),
+            &vault_count.to_le_bytes(),
+            &[ctx.bumps.vault],
+        ]];
+
+        // TRANSFER PAYOUT AT THE ORIGINAL TERMS FROM VAULT TO USER
+        let transfer_cpi_accounts = TransferChecked {
+            from: source_ata.to_account_info(),
+            mint: base_mint.to_account_info(),
+            to: ctx.accounts.destination_ata.to_account_info(),
+            authority: vault.to_account_info(),
+        };
+        let transfer_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            transfer_cpi_accounts,
+        )
+        .with_signer(signer_seed);
+        transfer_checked(transfer_ctx, amount_to_transfer, base_mint.decimals)?;
+
+        match vault.amount_redeemed.checked_add(amount_to_transfer) {
+            Some(result) => vault.amount_redeemed = result,
+            None => return err!(ErrorCode::Overflow),
+        }
+        match vault.opt_out_paid.checked_add(amount_to_transfer) {
+            Some(result) => vault.opt_out_paid = result,
+            None => return err!(ErrorCode::Overflow),
+        }
+        match vault.opt_out_redeemed.checked_add(user.amount) {
+            Some(result) => vault.opt_out_redeemed = result,
+            None => return err!(ErrorCode::Overflow),
+        }
+
+        // CLOSE USER AND RETURN RENT
+        vault.user_count -= 1;
+        user.close(ctx.accounts.owner.to_account_info())?;
+
+        Ok(())
+    }
+
+    pub fn deposit_first_loss(
+        ctx: Context<DepositFirstLoss>,
+        _vault_count: u64,
+        amount: u64,
+    ) -> Result<()> {
+        let base_mint = &ctx.accounts.base_mint;
+        let vault = &mut ctx.accounts.vault;
+
+        if vault.status != VaultStatus::Active {
+            return err!(ErrorCode::VaultSettled);
+        }
+
+        // TRANSFER AMOUNT FROM AUTHORITY TO FIRST-LOSS ACCOUNT
+        let transfer_cpi_accounts = TransferChecked {
+            from: ctx.accounts.source_ata.to_account_info(),
+            mint: base_mint.to_account_info(),
+            to: ctx.accounts.first_loss_ata.to_account_info(),
+            authority: ctx.accounts.authority.to_account_info(),
+        };
+        let transfer_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            transfer_cpi_accounts,
+        );
+        transfer_checked(transfer_ctx, amount, base_mint.decimals)?;
+
+        match vault.first_loss_amount.checked_add(amount) {
+            Some(result) => vault.first_loss_amount = result,
+            None => return err!(ErrorCode::Overflow),
+        }
+
+        Ok(())
+    }
+
+    pub fn reclaim_first_loss(ctx: Context<ReclaimFirstLoss>, vault_count: u64) -> Result<()> {
+        let base_mint = &ctx.accounts.base_mint;
+        let vault = &mut ctx.accounts.vault;
+        let first_loss_ata = &ctx.accounts.first_loss_ata;
+
+        // ONLY AFTER EVERY USER HAS BEEN PAID IN FULL
+        if (Clock::get()?.unix_timestamp * 1000) as u64 <= vault.end_date {
+            return err!(ErrorCode::VaultNotReady);
+        }
+        if vault.user_count > 0 {
+            return err!(ErrorCode::UsersOutstanding);
+        }
+
+        let signer_seed: &[&[&[u8]]] = &[&[
+            b"vault".as_ref(),
+            &vault_count.to_le_bytes(),
+            &[ctx.bumps.vault],
+        ]];
+
+        // TRANSFER REMAINING FIRST-LOSS CAPITAL TO AUTHORITY
+        let transfer_cpi_accounts = TransferChecked {
+            from: first_loss_ata.to_account_info(),
+            mint: base_mint.to_account_info(),
+            to: ctx.accounts.destination_ata.to_account_info(),
+            authority: vault.to_account_info(),
+        };
+        let transfer_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            transfer_cpi_accounts,
+        )
+        .with_signer(signer_seed);
+        transfer_checked(transfer_ctx, first_loss_ata.amount, base_mint.decimals)?;
+
+        // CLOSE FIRST-LOSS ACCOUNT AND RETURN RENT TO AUTHORITY
+        let close_cpi_accounts = CloseAccount {
+            account: first_loss_ata.to_account_info(),
+            destination: ctx.accounts.authority.to_account_info(),
+            authority: vault.to_account_info(),
+        };
+        let close_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            close_cpi_accounts,
+        )
+        .with_signer(signer_seed);
+        close_account(close_ctx)?;
+
+        vault.first_loss_amount = 0;
+
+        Ok(())
+    }
+
+    pub fn deposit_collateral(
+        ctx: Context<DepositCollateral>,
+        _vault_count: u64,
+        amount: u64,
+    ) -> Result<()> {
+        let collateral_mint = &ctx.accounts.collateral_mint;
+        let vault = &mut ctx.accounts.vault;
+
+        if vault.status != VaultStatus::Active {
+            return err!(ErrorCode::VaultSettled);
+        }
+
+        // TRANSFER COLLATERAL FROM AUTHORITY TO COLLATERAL ACCOUNT
+        let transfer_cpi_accounts = TransferChecked {
+            from: ctx.accounts.source_ata.to_account_info(),
+            mint: collateral_mint.to_account_info(),
+            to: ctx.accounts.collateral_ata.to_account_info(),
+            authority: ctx.accounts.authority.to_account_info(),
+        };
+        let transfer_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            transfer_cpi_accounts,
+        );
+        transfer_checked(transfer_ctx, amount, collateral_mint.decimals)?;
+
+        vault.collateral_decimals = collateral_mint.decimals;
+        match vault.collateral_deposited.checked_add(amount) {
+            Some(result) => vault.collateral_deposited = result,
+            None => return err!(ErrorCode::Overflow),
+        }
+
+        Ok(())
+    }
+
+    pub fn release_collateral(ctx: Context<ReleaseCollateral>, vault_count: u64) -> Result<()> {
+        let collateral_mint = &ctx.accounts.collateral_mint;
+        let vault = &mut ctx.accounts.vault;
+        let collateral_ata = &ctx.accounts.collateral_ata;
+
+        // ONLY AFTER FULL REPAYMENT, A DEFAULT, OR IF NO FUNDS WERE EVER COLLECTED
+        match vault.status {
+            // SEIZED COLLATERAL IS RELEASED IF THE VAULT IS REPAID IN FULL
+            VaultStatus::Settled => {}
+            // COLLATERAL BEYOND THE SHORTFALL IS RETURNED ONCE EVERY USER HAS LEFT
+            VaultStatus::Defaulted if vault.user_count > 0 => {
+                return err!(ErrorCode::CollateralOutstanding)
+            }
+            VaultStatus::Defaulted => {}
+            _ if vault.amount_collected != 0 => return err!(ErrorCode::VaultNotSettled),
+            _ => {}
+        }
+
+        let signer_seed: &[&[&[u8]]] = &[&[
+            b"vault".as_ref(),
+            &vault_count.to_le_bytes(),
+            &[ctx.bumps.vault],
+        ]];
+
+        // TRANSFER ALL COLLATERAL BACK TO AUTHORITY
+        let transfer_cpi_accounts = TransferChecked {
+            from: collateral_ata.to_account_info(),
+            mint: collateral_mint.to_account_info(),
+            to: ctx.accounts.destination_ata.to_account_info(),
+            authority: vault.to_account_info(),
+        };
+        let transfer_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            transfer_cpi_accounts,
+        )
+        .with_signer(signer_seed);
+        transfer_checked(
+            transfer_ctx,
+            collateral_ata.amount,
+            collateral_mint.decimals,
+        )?;
+
+        // CLOSE COLLATERAL ACCOUNT AND RETURN RENT TO AUTHORITY
+        let close_cpi_accounts = CloseAccount {
+            account: collateral_ata.to_account_info(),
+            destination: ctx.accounts.authority.to_account_info(),
+            authority: vault.to_account_info(),
+        };
+        let close_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            close_cpi_accounts,
+        )
+        .with_signer(signer_seed);
+        close_account(close_ctx)?;
+
+        vault.collateral_deposited = 0;
+
+        Ok(())
+    }
+
+    pub fn claim_collateral(ctx: Context<ClaimCollateral>, vault_count: u64) -> Result<()> {
+        // OVERSUBSCRIBED COMMITMENTS MUST BE ALLOCATED FIRST
+        if !state::Vault::is_allocated(&ctx.accounts.user, &ctx.accounts.vault) {
+            return err!(ErrorCode::AllocationPending);
+        }
+
+        let collateral_mint = &ctx.accounts.collateral_mint;
+        let vault = &mut ctx.accounts.vault;
+        let user = &mut ctx.accounts.user;
+        let tranche = &ctx.accounts.tranche;
+
+        if (vault.tranche_count > 0) != tranche.is_some() {
+            return err!(ErrorCode::InvalidTranche);
+        }
+
+        // COLLATERAL IS ONLY CLAIMABLE BY DEPOSITORS OF A DEFAULTED VAULT
+        if !state::Vault::collateral_outstanding(user, vault) {
+            return err!(ErrorCode::NothingToClaim);
+        }
+
+        // ONLY THE COLLATERAL WORTH THE USER'S SHORTFALL IS PAID OUT
+        let oracle = ctx
+            .accounts
+            .collateral_oracle
+            .as_ref()
+            .map(|oracle| oracle.to_account_info());
+        let now = (Clock::get()?.unix_timestamp * 1000) as u64;
+        let price = Price::load_collateral_price(vault, oracle.as_ref(), now)?;
+        let amount_to_transfer = match state::Vault::calculate_user_collateral(
+            user,
+            vault,
+            tranche.as_deref(),
+            price.as_ref(),
+            ctx.accounts.base_mint.decimals,
+        ) {
+            Some(result) => result,
+            None => return err!(ErrorCode::Overflow),
+        };
+
+        let signer_seed: &[&[&[u8]]] = &[&[
+            b"vault".as_ref(),
+            &vault_count.to_le_bytes(),
+            &[ctx.bumps.vault],
+        ]];
+
+        // TRANSFER PRO-RATA COLLATERAL TO USER
+        let transfer_cpi_accounts = TransferChecked {
+            from: ctx.accounts.collateral_ata.to_account_info(),
+            mint: collateral_mint.to_account_info(),
+            to: ctx.accounts.destination_ata.to_account_info(),
+            authority: vault.to_account_info(),
+        };
+        let transfer_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            transfer_cpi_accounts,
+        )
+        .with_signer(signer_seed);
+        transfer_checked(transfer_ctx, amount_to_transfer, collateral_mint.decimals)?;
+
+        match vault.collateral_claimed.checked_add(amount_to_transfer) {
+            Some(result) => vault.collateral_claimed = result,
+            None => return err!(ErrorCode::Overflow),
+        }
+        user.collateral_claimed = true;
+
+        // CLOSE USER AND RETURN RENT IF THE PAYOUT WAS ALREADY CLAIMED
+        let payout = state::Vault::calculate_user_payout(user, vault, tranche.as_deref());
+        if user.amount_claimed == payout {
+            vault.user_count -= 1;
+            user.close(ctx.accounts.owner.to_account_info())?;
+        }
+
+        Ok(())
+    }
+
+    pub fn liquidate(ctx: Context<Liquidate>, vault_count: u64) -> Result<()> {
+        let collateral_mint = &ctx.accounts.collateral_mint;
+        let vault = &mut ctx.accounts.vault;
+        let now = (Clock::get()?.unix_timestamp * 1000) as u64;
+
+        if vault.status != VaultStatus::Active {
+            return err!(ErrorCode::VaultNotActive);
+        }
+
+        // COLLATERAL MUST BE BELOW THE MAINTENANCE RATIO OF THE OUTSTANDING PRINCIPAL
+        let oracle = ctx
+            .accounts
+            .collateral_oracle
+            .as_ref()
+            .map(|oracle| oracle.to_account_info());
+        let price = Price::load_collateral_price(vault, oracle.as_ref(), now)?;
+        if !state::Vault::is_liquidatable(vault, price.as_ref(), ctx.accounts.base_mint.decimals) {
+            return err!(ErrorCode::CollateralHealthy);
+        }
+
+        let collateral_remaining = vault.collateral_deposited - vault.collateral_claimed;
+        let bounty = match (collateral_remaining as u128)
+            .checked_mul(vault.liquidation_bounty_bps as u128)
+        {
+            Some(result) => (result / 10_000) as u64,
+            None => return err!(ErrorCode::Overflow),
+        };
+
+        let signer_seed: &[&[&[u8]]] = &[&[
+            b"vault".as_ref(),
+            &vault_count.to_le_bytes(),
+            &[ctx.bumps.vault],
+        ]];
+
+        // PAY THE KEEPER BOUNTY FROM THE SEIZED COLLATERAL
+        if bounty > 0 {
+            let transfer_cpi_accounts = TransferChecked {
+                from: ctx.accounts.collateral_ata.to_account_info(),
+                mint: collateral_mint.to_account_info(),
+                to: ctx.accounts.keeper_ata.to_account_info(),
+                authority: vault.to_account_info(),
+            };
+            let transfer_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                transfer_cpi_accounts,
+            )
+            .with_signer(signer_seed);
+            transfer_checked(transfer_ctx, bounty, collateral_mint.decimals)?;
+        }
+
+        // REMAINING COLLATERAL COVERS THE SHORTFALL OF USERS IF THE VAULT DEFAULTS
+        vault.collateral_deposited -= bounty;
+        vault.status = VaultStatus::Liquidating;
+
+        Ok(())
+    }
+
+    pub fn settle_vault<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SettleVault<'info>>,
+        vault_count: u64,
+    ) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        let now = (Clock::get()?.unix_timestamp * 1000) as u64;
+
+        if vault.amortization != AmortizationStyle::Bullet {
+            return err!(ErrorCode::InvalidAmortization);
+        }
+        if vault.status != VaultStatus::Active && vault.status != VaultStatus::Liquidating {
+            return err!(ErrorCode::VaultSettled);
+        }
+        if now <= vault.end_date {
+            return err!(ErrorCode::VaultNotReady);
+        }
+        if state::Vault::allocation_pending(vault) {
+            return err!(ErrorCode::AllocationPending);
+        }
+        if state::Vault::extension_pending(vault) {
+            return err!(ErrorCode::ExtensionPending);
+        }
+
+        // FIRST-LOSS CAPITAL MUST BE COUNTED WHENEVER THE VAULT HOLDS ANY
+        let first_loss_amount = match &ctx.accounts.first_loss_ata {
+            Some(first_loss_ata) => first_loss_ata.amount,
+            None if vault.first_loss_amount > 0 => return err!(ErrorCode::FirstLossRequired),
+            None => 0,
+        };
+
+        let mut tranches = load_tranches(vault_count, vault, ctx.remaining_accounts)?;
+        let assets = ctx.accounts.vault_ata.amount - vault.waitlist_amount + first_loss_amount;
+        vault.settle(
+            assets,
+            tranches.iter_mut().map(|tranche| &mut **tranche),
+            now,
+        );
+
+        // A SHORTFALL CAN ONLY BE LOCKED IN AFTER THE GRACE PERIOD
+        if vault.status == VaultStatus::Defaulted && now < vault.end_date + vault.grace_period {
+            return err!(ErrorCode::GracePeriod);
+        }
+
+        // ONLY AUTHORITY CAN LOCK IN A SHORTFALL BEFORE WITHDRAW TIMEFRAME ENDS
+        if vault.status == VaultStatus::Defaulted
+            && ctx.accounts.payer.key() != vault.authority
+            && now < vault.end_date + vault.withdraw_timeframe
+        {
+            return err!(ErrorCode::VaultNotReady);
+        }
+
+        for tranche in tranches {
+            tranche.exit(&crate::ID)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn get_liabilities<'info>(
+        ctx: Context<'_, '_, 'info, 'info, GetLiabilities<'info>>,
+        vault_count: u64,
+    ) -> Result<u64> {
+        let mut vault = ctx.accounts.vault.clone().into_inner();
+
+        // LATE PENALTY ACCRUES UNTIL SETTLEMENT
+        if vault.status == VaultStatus::Active || vault.status == VaultStatus::Liquidating {
+            vault.late_days =
+                state::Vault::late_days(&vault, (Clock::get()?.unix_timestamp * 1000) as u64);
+        }
+
+        if vault.tranche_count == 0 {
+            return Ok(vault.outstanding_owed());
+        }
+        let mut liabilities: u64 = 0;
+        for tranche in load_tranches(vault_count, &vault, ctx.remaining_accounts)? {
+            match liabilities.checked_add(tranche.outstanding_owed(&vault)) {
+                Some(result) => liabilities = result,
+                None => return err!(ErrorCode::Overflow),
+            }
+        }
+
+        Ok(liabilities)
+    }
+
+    pub fn close_vault(
+        ctx: Context<CloseVault>,
+        vault_count: u64,
+        _authority: Pubkey,
+    ) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        let source_ata = &ctx.accounts.source_ata;
+        let destination_ata = &ctx.accounts.destination_ata;
+        let base_mint = &ctx.accounts.base_mint;
+        let now = (Clock::get()?.unix_timestamp * 1000) as u64;
+
+        // PDA REQUIRE SEED SIGNER
+        let signer_seed: &[&[&[u8]]] = &[&[
+            b"vault".as_ref(),
+            &vault_count.to_le_bytes(),
+            &[ctx.bumps.vault],
+        ]];
+
+        // PERPETUAL VAULT CAN ONLY CLOSE ONCE ALL SHARES ARE REDEEMED AND QUEUED WITHDRAWALS CLAIMED
+        if vault.mode == VaultMode::Perpetual {
+            if vault.queue_claimed < vault.queue_requested {
+                return err!(ErrorCode::WithdrawQueueOutstanding);
+            }
+            match &ctx.accounts.share_mint {
+                Some(share_mint) if share_mint.supply > 0 => {
+                    return err!(ErrorCode::SharesOutstanding)
+                }
+                Some(_) => {}
+                None if vault.share_mint != Pubkey::default() => {
+                    return err!(ErrorCode::InvalidMint)
+                }
+                None => {}
+            }
+        }
+
+        // USERS STILL TO CLAIM KEEP THEIR PAYOUT RESERVED, ONLY THE SURPLUS IS RELEASED
+        if vault.user_count > 0 {
+            if vault.end_date + vault.withdraw_timeframe > now {
+                return err!(ErrorCode::VaultNotReady);
+            }
+            // PAYOUTS ARE FINAL ONLY ONCE SETTLED THROUGH SETTLE_VAULT
+            if vault.status == VaultStatus::Active || vault.status == VaultStatus::Liquidating {
+                return err!(ErrorCode::VaultNotSettled);
+            }
+
+            let surplus = source_ata
+                .amount
+                .saturating_sub(vault.settled_reserve() + vault.waitlist_amount);
+            if surplus > 0 {
+                let transfer_cpi_accounts = TransferChecked {
+                    from: source_ata.to_account_info(),
+                    mint: base_mint.to_account_info(),
+                    to: destination_ata.to_account_info(),
+                    authority: vault.to_account_info(),
+                };
+                let transfer_ctx = CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    transfer_cpi_accounts,
+                )
+                .with_signer(signer_seed);
+                transfer_checked(transfer_ctx, surplus, base_mint.decimals)?;
+            }
+
+            return Ok(());
+        }
+
+        if vault.end_date + vault.withdraw_timeframe > now && source_ata.amount > 0 {
+            return err!(ErrorCode::VaultNotReady);
+        }
+        if vault.first_loss_amount > 0 {
+            return err!(ErrorCode::FirstLossOutstanding);
+        }
+        if vault.waitlist_amount > 0 {
+            return err!(ErrorCode::WaitlistOutstanding);
+        }
+        if vault.collateral_deposited > vault.collateral_claimed {
+            return err!(ErrorCode::CollateralOutstanding);
+        }
+
+        // TRANSNFER ALL FUNDS IN VAULT TO AUTHORITY
+        let transfer_cpi_accounts = TransferChecked {
+            from: source_ata.to_account_info(),
+            mint: base_mint.to_account_info(),
+            to: destination_ata.to_account_info(),
+            authority: vault.to_account_info(),
+        };
+        let transfer_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            transfer_cpi_accounts,
+        )
+        .with_signer(signer_seed);
+        transfer_checked(transfer_ctx, source_ata.amount, base_mint.decimals)?;
+
+        // CLOSE VAULT AND TRANSFER RENT TO CREATOR
+        let close_cpi_accounts = CloseAccount {
+            account: source_ata.to_account_info(),
+            destination: ctx.accounts.creator.to_account_info(),
+            authority: vault.to_account_info(),
+        };
+        let close_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            close_cpi_accounts,
+        )
+        .with_signer(signer_seed);
+        close_account(close_ctx)?;
+
+        vault.close(ctx.accounts.creator.to_account_info())?;
+
+        Ok(())
+    }
+
+    pub fn sweep_user(ctx: Context<SweepUser>, vault_count: u64) -> Result<()> {
+        let vault_info = ctx.accounts.vault.to_account_info();
+        let user = &mut ctx.accounts.user;
+        let tranche = &mut ctx.accounts.tranche;
+        let owner = ctx.accounts.owner.to_account_info();
+
+        // VAULT ALREADY CLOSED, ONLY THE RENT IS LEFT TO RETURN
+        if vault_info.data_is_empty() {
+            user.close(owner)?;
+            return Ok(());
+        }
+        if vault_info.owner != &crate::ID {
+            return err!(ErrorCode::Unauthorized);
+        }
+        let mut vault = {
+            let data = vault_info.try_borrow_data()?;
+            state::Vault::try_deserialize(&mut &data[..])?
+        };
+
+        // ONLY ONCE THE VAULT CAN BE CLOSED AND PAYOUTS ARE FINAL
+        let now = (Clock::get()?.unix_timestamp * 1000) as u64;
+        if vault.end_date + vault.withdraw_timeframe > now || user.is_vote_locked(now) {
+            return err!(ErrorCode::VaultNotReady);
+        }
+        if vault.status != VaultStatus::Settled && vault.status != VaultStatus::Defaulted {
+            return err!(ErrorCode::VaultNotSettled);
+        }
+        if (vault.tranche_count > 0) != tranche.is_some() {
+            return err!(ErrorCode::InvalidTranche);
+        }
+        let signer_seed: &[&[&[u8]]] = &[&[
+            b"vault".as_ref(),
+            &vault_count.to_le_bytes(),
+            &[ctx.bumps.vault],
+        ]];
+
+        // PAY THE UNCLAIMED COLLATERAL SO ONE IDLE USER CANNOT KEEP THE VAULT OPEN
+        if state::Vault::collateral_outstanding(user, &vault) {
+            let (collateral_ata, collateral_destination_ata, collateral_mint, base_mint) = match (
+                &ctx.accounts.collateral_ata,
+                &ctx.accounts.collateral_destination_ata,
+                &ctx.accounts.collateral_mint,
+                &ctx.accounts.base_mint,
+            ) {
+                (
+                    Some(collateral_ata),
+                    Some(collateral_destination_ata),
+                    Some(collateral_mint),
+                    Some(base_mint),
+                ) => (
+                    collateral_ata,
+                    collateral_destination_ata,
+                    collateral_mint,
+                    base_mint,
+                ),
+                _ => return err!(ErrorCode::CollateralOutstanding),
+            };
+            if collateral_mint.key() != vault.collateral_mint || base_mint.key() != vault.base_mint
+            {
+                return err!(ErrorCode::InvalidMint);
+            }
+
+            let oracle = ctx
+                .accounts
+                .collateral_oracle
+                .as_ref()
+                .map(|oracle| oracle.to_account_info());
+            let price = Price::load_collateral_price(&vault, oracle.as_ref(), now)?;
+            let amount_to_transfer = match state::Vault::calculate_user_collateral(
+                user,
+                &vault,
+                tranche.as_deref(),
+                price.as_ref(),
+                base_mint.decimals,
+            ) {
+                Some(result) => result,
+                None => return err!(ErrorCode::Overflow),
+            };
+
+            let transfer_cpi_accounts = TransferChecked {
+                from: collateral_ata.to_account_info(),
+                mint: collateral_mint.to_account_info(),
+                to: collateral_destination_ata.to_account_info(),
+                authority: vault_info.clone(),
+            };
+            let transfer_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                transfer_cpi_accounts,
+            )
+            .with_signer(signer_seed);
+            transfer_checked(transfer_ctx, amount_to_transfer, collateral_mint.decimals)?;
+
+            match vault.collateral_claimed.checked_add(amount_to_transfer) {
+                Some(result) => vault.collateral_claimed = result,
+                None => return err!(ErrorCode::Overflow),
+            }
+            user.collateral_claimed = true;
+        }
+        if !state::Vault::is_allocated(user, &vault) {
+            return err!(ErrorCode::AllocationPending);
+        }
+
+        // PAY THE UNCLAIMED BALANCE TO THE OWNER
+        let amount_to_transfer = match vault.amortization {
+            AmortizationStyle::Bullet => {
+                let payout = state::Vault::calculate_user_payout(user, &vault, tranche.as_deref());
+                payout.saturating_sub(user.amount_claimed)
+            }
+            _ => match state::Vault::calculate_user_instalments(user, &vault) {
+                Some(result) => result,
+                None => return err!(ErrorCode::Overflow),
+            },
+        };
+        if amount_to_transfer > 0 {
+            let (source_ata, destination_ata, base_mint) = match (
+                &ctx.accounts.source_ata,
+                &ctx.accounts.destination_ata,
+                &ctx.accounts.base_mint,
+            ) {
+                (Some(source_ata), Some(destination_ata), Some(base_mint)) => {
+                    (source_ata, destination_ata, base_mint)
+                }
+                _ => return err!(ErrorCode::InvalidTokenAccount),
+            };
+            if base_mint.key() != vault.base_mint {
+                return err!(ErrorCode::InvalidMint);
+            }
+
+            let transfer_cpi_accounts = TransferChecked {
+                from: source_ata.to_account_info(),
+                mint: base_mint.to_account_info(),
+                to: destination_ata.to_account_info(),
+                authority: vault_info.clone(),
+            };
+            let transfer_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                transfer_cpi_accounts,
+            )
+            .with_signer(signer_seed);
+            transfer_checked(transfer_ctx, amount_to_transfer, base_mint.decimals)?;
+
+            match vault.amount_redeemed.checked_add(amount_to_transfer) {
+                Some(result) => vault.amount_redeemed = result,
+                None => return err!(ErrorCode::Overflow),
+            }
+            if let Some(tranche) = tranche {
+                match tranche.amount_redeemed.checked_add(amount_to_transfer) {
+                    Some(result) => tranche.amount_redeemed = result,
+                    None => return err!(ErrorCode::Overflow),
+                }
+            }
+        }
+
+        // CLOSE USER AND RETURN RENT TO OWNER
+        vault.user_count -= 1;
+        vault.try_serialize(&mut &mut vault_info.try_borrow_mut_data()?[..])?;
+        user.close(owner)?;
+
+        Ok(())
+    }
+
+    pub fn init_share_mint(ctx: Context<InitShareMint>, _vault_count: u64) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+
+        if vault.share_mint != Pubkey::default() {
+            return err!(ErrorCode::ShareMintInitialized);
+        }
+        vault.share_mint = ctx.accounts.share_mint.key();
+
+        Ok(())
+    }
+
+    pub fn deposit_assets(
+        ctx: Context<ShareDeposit>,
+        vault_count: u64,
+        assets: u64,
+    ) -> Result<u64> {
+        let vault = &ctx.accounts.vault;
+        let total_assets = vault.total_assets(ctx.accounts.vault_ata.amount);
+
+        // SHARES ROUND DOWN IN VAULT'S FAVOUR
+        let shares = match state::Vault::convert_to_shares(
+            assets,
+            total_assets,
+            ctx.accounts.share_mint.supply,
+            Rounding::Down,
+        ) {
+            Some(result) => result,
+            None => return err!(ErrorCode::Overflow),
+        };
+
+        share_deposit(ctx, vault_count, assets, shares)?;
+
+        Ok(shares)
+    }
+
+    pub fn mint_shares(ctx: Context<ShareDeposit>, vault_count: u64, shares: u64) -> Result<u64> {
+        let vault = &ctx.accounts.vault;
+        let total_assets = vault.total_assets(ctx.accounts.vault_ata.amount);
+
+        // ASSETS ROUND UP IN VAULT'S FAVOUR
+        let assets = match state::Vault::convert_to_assets(
+            shares,
+            total_assets,
+            ctx.accounts.share_mint.supply,
+            Rounding::Up,
+        ) {
+            Some(result) => result,
+            None => return err!(ErrorCode::Overflow),
+        };
+
+        share_deposit(ctx, vault_count, assets, shares)?;
+
+        Ok(assets)
+    }
+
+    pub fn withdraw_assets(
+        ctx: Context<ShareWithdraw>,
+        vault_count: u64,
+        assets: u64,
+    ) -> Result<u64> {
+        let vault = &ctx.accounts.vault;
+        let total_assets = vault.total_assets(ctx.accounts.vault_ata.amount);
+
+        // SHARES ROUND UP IN VAULT'S FAVOUR
+        let shares = match state::Vault::convert_to_shares(
+            assets,
+            total_assets,
+            ctx.accounts.share_mint.supply,
+            Rounding::Up,
+        ) {
+            Some(result) => result,
+            None => return err!(ErrorCode::Overflow),
+        };
+
+        share_withdraw(ctx, vault_count, assets, shares)?;
+
+        Ok(shares)
+    }
+
+    pub fn redeem_shares(
+        ctx: Context<ShareWithdraw>,
+        vault_count: u64,
+        shares: u64,
+    ) -> Result<u64> {
+        let vault = &ctx.accounts.vault;
+        let total_assets = vault.total_assets(ctx.accounts.vault_ata.amount);
+
+        // ASSETS ROUND DOWN IN VAULT'S FAVOUR
+        let assets = match state::Vault::convert_to_assets(
+            shares,
+            total_assets,
+            ctx.accounts.share_mint.supply,
+            Rounding::Down,
+        ) {
+            Some(result) => result,
+            None => return err!(ErrorCode::Overflow),
+        };
+
+        share_withdraw(ctx, vault_count, assets, shares)?;
+
+        Ok(assets)
+    }
+
+    pub fn report_total_assets(
+        ctx: Context<ReportTotalAssets>,
+        _vault_count: u64,
+        total_assets: u64,
+    ) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+
+        if vault.asset_source != AssetSource::Reported {
+            return err!(ErrorCode::InvalidVaultMode);
+        }
+        vault.total_assets = total_assets;
+
+        Ok(())
+    }
+
+    pub fn request_withdraw(
+        ctx: Context<RequestWithdraw>,
+        vault_count: u64,
+        shares: u64,
+    ) -> Result<u64> {
+        let owner = &ctx.accounts.owner;
+        let vault = &mut ctx.accounts.vault;
+        let vault_ata = &ctx.accounts.vault_ata;
+        let withdraw_request = &mut ctx.accounts.withdraw_request;
+
+        // ASSETS ROUND DOWN IN VAULT'S FAVOUR
+        let total_assets = vault.total_assets(vault_ata.amount);
+        let assets = match state::Vault::convert_to_assets(
+            shares,
+            total_assets,
+            ctx.accounts.share_mint.supply,
+            Rounding::Down,
+        ) {
+            Some(result) => result,
+            None => return err!(ErrorCode::Overflow),
+        };
+
+        if assets == 0 || shares == 0 {
+            return err!(ErrorCode::ZeroAmount);
+        }
+
+        // BURN SHARES FROM USER
+        let burn_cpi_accounts = Burn {
+            mint: ctx.accounts.share_mint.to_account_info(),
+            from: ctx.accounts.share_ata.to_account_info(),
+            authority: owner.to_account_info(),
+        };
+        let burn_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            burn_cpi_accounts,
+        );
+        burn(burn_ctx, shares)?;
+
+        if vault.asset_source == AssetSource::Reported {
+            vault.total_assets = vault.total_assets.saturating_sub(assets);
+        }
+
+        withdraw_request.vault_count = vault_count;
+        withdraw_request.request_id = vault.queue_count;
+        withdraw_request.owner = owner.key();
+        withdraw_request.assets = assets;
+        match vault.queue_requested.checked_add(assets) {
+            Some(result) => {
+                vault.queue_requested = result;
+                withdraw_request.cumulative_end = result;
+            }
+            None => return err!(ErrorCode::Overflow),
+        }
+        match vault.queue_count.checked_add(1) {
+            Some(result) => vault.queue_count = result,
+            None => return err!(ErrorCode::Overflow),
+        }
+
+        // FILL FROM IDLE LIQUIDITY IF AVAILABLE
+        vault.fill_queue(vault_ata.amount);
+
+        Ok(assets)
+    }
+
+    pub fn claim_withdraw(
+        ctx: Context<ClaimWithdraw>,
+        vault_count: u64,
+        _request_id: u64,
+    ) -> Result<()> {
+        let base_mint = &ctx.accounts.base_mint;
+        let vault = &mut ctx.accounts.vault;
+        let withdraw_request = &ctx.accounts.withdraw_request;
+
+        if vault.queue_filled < withdraw_request.cumulative_end {
+            return err!(ErrorCode::RequestNotFilled);
+        }
+
+        let signer_seed: &[&[&[u8]]] = &[&[
+            b"vault".as_ref(),
+            &vault_count.to_le_bytes(),
+            &[ctx.bumps.vault],
+        ]];
+
+        // TRANSFER FILLED ASSETS FROM VAULT TO USER
+        let transfer_cpi_accounts = TransferChecked {
+            from: ctx.accounts.vault_ata.to_account_info(),
+            mint: base_mint.to_account_info(),
+            to: ctx.accounts.destination_ata.to_account_info(),
+            authority: vault.to_account_info(),
+        };
+        let transfer_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            transfer_cpi_accounts,
+        )
+        .with_signer(signer_seed);
+        transfer_checked(transfer_ctx, withdraw_request.assets, base_mint.decimals)?;
+
+        match vault.queue_claimed.checked_add(withdraw_request.assets) {
+            Some(result) => vault.queue_claimed = result,
+            None => return err!(ErrorCode::Overflow),
+        }
+        match vault.amount_redeemed.checked_add(withdraw_request.assets) {
+            Some(result) => vault.amount_redeemed = result,
+            None => return err!(ErrorCode::Overflow),
+        }
+
+        Ok(())
+    }
+
+    pub fn repay<'info>(
+        ctx: Context<'_, '_, 'info, 'info, Repay<'info>>,
+        vault_count: u64,
+        amount: u64,
+    ) -> Result<()> {
+        let base_mint = &ctx.accounts.base_mint;
+        let vault = &mut ctx.accounts.vault;
+        let vault_ata = &ctx.accounts.vault_ata;
+        let now = (Clock::get()?.unix_timestamp * 1000) as u64;
+
+        // TRANSFER AMOUNT FROM AUTHORITY TO VAULT ATA
+        let transfer_cpi_accounts = TransferChecked {
+            from: ctx.accounts.source_ata.to_account_info(),
+            mint: base_mint.to_account_info(),
+            to: vault_ata.to_account_info(),
+            authority: ctx.accounts.authority.to_account_info(),
+        };
+        let transfer_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            transfer_cpi_accounts,
+        );
+        transfer_checked(transfer_ctx, amount, base_mint.decimals)?;
+
+        match vault.amount_repaid.checked_add(amount) {
+            Some(result) => vault.amount_repaid = result,
+            None => return err!(ErrorCode::Overflow),
+        }
+
+        // REPAYMENT FILLS QUEUED WITHDRAWALS IN ORDER
+        let balance = match vault_ata.amount.checked_add(amount) {
+            Some(result) => result,
+            None => return err!(ErrorCode::Overflow),
+        };
+        vault.fill_queue(balance);
+
+        // LATE PENALTY STOPS ONCE THE VAULT ATA FIRST COVERS EVERYTHING OWED
+        if vault.mode == VaultMode::FixedTerm
+            && vault.amortization == AmortizationStyle::Bullet
+            && vault.status == VaultStatus::Active
+            && vault.repaid_at == 0
+            && now >= state::Vault::deposit_close(vault)
+        {
+            let tranches = load_tranches(vault_count, vault, ctx.remaining_accounts)?;
+            if balance - vault.waitlist_amount >= vault.liabilities_at(&tranches, now) {
+                vault.repaid_at = now;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn share_deposit(
+    ctx: Context<ShareDeposit>,
+    vault_count: u64,
+    assets: u64,
+    shares: u64,
+) -> Result<()> {
+    let owner = &ctx.accounts.owner;
+    let source_ata = &ctx.accounts.source_ata;
+    let base_mint = &ctx.accounts.base_mint;
+    let vault = &mut ctx.accounts.vault;
+    let vault_ata = &ctx.accounts.vault_ata;
+
+    if assets == 0 || shares == 0 {
+        return err!(ErrorCode::ZeroAmount);
+    }
+
+    let total_assets = vault.total_assets(vault_ata.amount);
+    match total_assets.checked_add(assets) {
+        Some(result) if result <= vault.vault_capacity => {}
+        Some(_) => return err!(ErrorCode::AmountExceedVaultCapacity),
+        None => return err!(ErrorCode::Overflow),
+    }
+
+    // TRANSFER ASSETS FROM USER TO VAULT ATA
+    let transfer_cpi_accounts = TransferChecked {
+        from: source_ata.to_account_info(),
+        mint: base_mint.to_account_info(),
+        to: vault_ata.to_account_info(),
+        authority: owner.to_account_info(),
+    };
+    let transfer_ctx = CpiContext::new(
+        ctx.accounts.token_program.to_account_info(),
+        transfer_cpi_accounts,
+    );
+    transfer_checked(transfer_ctx, assets, base_mint.decimals)?;
+
+    // MINT SHARES TO USER
+    let signer_seed: &[&[&[u8]]] = &[&[
+        b"vault".as_ref(),
+        &vault_count.to_le_bytes(),
+        &[ctx.bumps.vault],
+    ]];
+    let mint_cpi_accounts = MintTo {
+        mint: ctx.accounts.share_mint.to_account_info(),
+        to: ctx.accounts.share_ata.to_account_info(),
+        authority: vault.to_account_info(),
+    };
+    let mint_ctx = CpiContext::new(
+        ctx.accounts.token_program.to_account_info(),
+        mint_cpi_accounts,
+    )
+    .with_signer(signer_seed);
+    mint_to(mint_ctx, shares)?;
+
+    if vault.asset_source == AssetSource::Reported {
+        match vault.total_assets.checked_add(assets) {
+            Some(result) => vault.total_assets = result,
+            None => return err!(ErrorCode::Overflow),
+        }
+    }
+    match vault.amount_collected.checked_add(assets) {
+        Some(result) => vault.amount_collected = result,
+        None => return err!(ErrorCode::Overflow),
+    }
+
+    // NEW DEPOSITS FILL QUEUED WITHDRAWALS
+    match vault_ata.amount.checked_add(assets) {
+        Some(result) => vault.fill_queue(result),
+        None => return err!(ErrorCode::Overflow),
+    };
+
+    Ok(())
+}
+
+fn share_withdraw(
+    ctx: Context<ShareWithdraw>,
+    vault_count: u64,
+    assets: u64,
+    shares: u64,
+) -> Result<()> {
+    let owner = &ctx.accounts.owner;
+    let base_mint = &ctx.accounts.base_mint;
+    let vault = &mut ctx.accounts.vault;
+    let vault_ata = &ctx.accounts.vault_ata;
+
+    if assets == 0 || shares == 0 {
+        return err!(ErrorCode::ZeroAmount);
+    }
+
+    // QUEUED WITHDRAWALS ARE SERVED FIRST
+    if vault.available_liquidity(vault_ata.amount) < assets {
+        return err!(ErrorCode::InsufficientLiquidity);
+    }
+
+    // BURN SHARES FROM USER
+    let burn_cpi_accounts = Burn {
+        mint: ctx.accounts.share_mint.to_account_info(),
+        from: ctx.accounts.share_ata.to_account_info(),
+        authority: owner.to_account_info(),
+    };
+    let burn_ctx = CpiContext::new(
+        ctx.accounts.token_program.to_account_info(),
+        burn_cpi_accounts,
+    );
+    burn(burn_ctx, shares)?;
+
+    // TRANSFER ASSETS FROM VAULT TO USER
+    let signer_seed: &[&[&[u8]]] = &[&[
+        b"vault".as_ref(),
+        &vault_count.to_le_bytes(),
+        &[ctx.bumps.vault],
+    ]];
+    let transfer_cpi_accounts = TransferChecked {
+        from: vault_ata.to_account_info(),
+        mint: base_mint.to_account_info(),
+        to: ctx.accounts.destination_ata.to_account_info(),
+        authority: vault.to_account_info(),
+    };
+    let transfer_ctx = CpiContext::new(
+        ctx.accounts.token_program.to_account_info(),
+        transfer_cpi_accounts,
+    )
+    .with_signer(signer_seed);
+    transfer_checked(transfer_ctx, assets, base_mint.decimals)?;
+
+    if vault.asset_source == AssetSource::Reported {
+        vault.total_assets = vault.total_assets.saturating_sub(assets);
+    }
+    match vault.amount_redeemed.checked_add(assets) {
+        Some(result) => vault.amount_redeemed = result,
+        None => return err!(ErrorCode::Overflow),
+    }
+
+    Ok(())
+}
+
+// Applies the terms that can still change once funds have been collected, only in favour of
+// depositors unless approved by a governance vote
+fn amend_vault(
+    vault: &mut state::Vault,
+    params: &InitOrUpdateVaultParam,
+    approved: bool,
+    now: u64,
+) -> Result<()> {
+    if vault.status != VaultStatus::Active || state::Vault::extension_pending(vault) {
+        return err!(ErrorCode::NotUpdatable);
+    }
+    require_unchanged!(params.min_amount, min_amount, vault);
+    require_unchanged!(params.start_date, start_date, vault);
+    require_unchanged!(params.withdraw_timeframe, withdraw_timeframe, vault);
+    require_unchanged!(params.mode, mode, vault);
+    require_unchanged!(params.asset_source, asset_source, vault);
+    require_unchanged!(params.early_withdraw, early_withdraw, vault);
+    require_unchanged!(
+        params.early_withdraw_penalty_bps,
+        early_withdraw_penalty_bps,
+        vault
+    );
+    require_unchanged!(params.penalty_decay, penalty_decay, vault);
+    require_unchanged!(params.penalty_recipient, penalty_recipient, vault);
+    require_unchanged!(params.vesting_period, vesting_period, vault);
+    require_unchanged!(params.coupon_frequency, coupon_frequency, vault);
+    require_unchanged!(params.amortization, amortization, vault);
+    require_unchanged!(params.instalment_count, instalment_count, vault);
+    require_unchanged!(params.collateral_mint, collateral_mint, vault);
+    require_unchanged!(params.collateral_ratio_bps, collateral_ratio_bps, vault);
+    require_unchanged!(params.collateral_oracle, collateral_oracle, vault);
+    require_unchanged!(params.oracle_max_staleness, oracle_max_staleness, vault);
+    require_unchanged!(params.oracle_max_conf_bps, oracle_max_conf_bps, vault);
+    require_unchanged!(params.maintenance_ratio_bps, maintenance_ratio_bps, vault);
+    require_unchanged!(params.liquidation_bounty_bps, liquidation_bounty_bps, vault);
+    require_unchanged!(params.require_yield_reserve, require_yield_reserve, vault);
+    require_unchanged!(params.grace_period, grace_period, vault);
+    require_unchanged!(params.late_penalty_bps, late_penalty_bps, vault);
+    require_unchanged!(params.allowlist_root, allowlist_root, vault);
+    require_unchanged!(params.kyc_issuer, kyc_issuer, vault);
+    require_unchanged!(params.max_per_user, max_per_user, vault);
+    require_unchanged!(params.tier_caps, tier_caps, vault);
+    require_unchanged!(params.oversubscription, oversubscription, vault);
+    require_unchanged!(params.deposit_open, deposit_open, vault);
+    require_unchanged!(params.deposit_close, deposit_close, vault);
+    require_unchanged!(params.governance_quorum_bps, governance_quorum_bps, vault);
+    require_unchanged!(
+        params.governance_threshold_bps,
+        governance_threshold_bps,
+        vault
+    );
+    require_unchanged!(params.voting_period, voting_period, vault);
+
+    // YIELD CAN ONLY BE RAISED WITHOUT APPROVAL, AND NEVER ONCE COUPONS OR INSTALMENTS
+    // WERE PAID AT THE OLD YIELD
+    let yield_bps = params.yield_bps.unwrap_or(vault.yield_bps);
+    if (yield_bps < vault.yield_bps && !approved)
+        || (yield_bps != vault.yield_bps
+            && (vault.coupons_paid > 0 || vault.instalments_repaid > 0))
+    {
+        return err!(ErrorCode::NotUpdatable);
+    }
+
+    // CAPACITY CAN ONLY BE RAISED WITHOUT APPROVAL, AND NOT ONCE COMMITMENTS HAVE BEEN ALLOCATED
+    // OR WHEN IT IS SPLIT INTO TRANCHES
+    let vault_capacity = params.vault_capacity.unwrap_or(vault.vault_capacity);
+    if (vault_capacity < vault.vault_capacity
+        && (!approved || vault_capacity < vault.amount_collected))
+        || (vault_capacity != vault.vault_capacity
+            && (vault.allocation_finalized || vault.tranche_count > 0))
+    {
+        return err!(ErrorCode::NotUpdatable);
+    }
+
+    // TERM CAN ONLY BE SHORTENED WITHOUT APPROVAL IF THE YIELD OVER THE TERM DOES NOT FALL,
+    // AND NEVER WITHIN A SCHEDULE
+    let end_date = params.end_date.unwrap_or(vault.end_date);
+    if end_date != vault.end_date {
+        let term_yield = (yield_bps as u128) * (end_date.saturating_sub(vault.start_date) as u128);
+        let current_term_yield =
+            (vault.yield_bps as u128) * ((vault.end_date - vault.start_date) as u128);
+        if ((end_date > vault.end_date || term_yield < current_term_yield) && !approved)
+            || vault.coupon_interval != 0
+            || vault.amortization != AmortizationStyle::Bullet
+        {
+            return err!(ErrorCode::NotUpdatable);
+        }
+        if end_date <= vault.start_date || end_date <= vault.original_end_date || end_date <= now {
+            return err!(ErrorCode::InvalidEndTimeInput);
+        }
+    }
+
+    // A REPAYMENT ON THE PREVIOUS TERMS MAY NO LONGER COVER WHAT IS OWED
+    if yield_bps != vault.yield_bps || end_date != vault.end_date {
+        vault.repaid_at = 0;
+    }
+    vault.yield_bps = yield_bps;
+    vault.vault_capacity = vault_capacity;
+    vault.end_date = end_date;
+
+    Ok(())
+}
+
+fn promote_waitlist_entry(
+    vault: &mut state::Vault,
+    waitlist_entry: &mut state::WaitlistEntry,
+    user: &mut state::User,
+) -> Result<()> {
+    let amount = waitlist_entry.amount;
+
+    // ENTRIES LEFT BY THEIR OWNER ARE SKIPPED
+    vault.waitlist_head += 1;
+    if amount == 0 {
+        return Ok(());
+    }
+
+    match vault.amount_collected.checked_add(amount) {
+        Some(result) => vault.amount_collected = result,
+        None => return err!(ErrorCode::Overflow),
+    }
+    vault.waitlist_amount -= amount;
+    waitlist_entry.amount = 0;
+
+    // NEW USER ACCOUNT
+    if user.amount == 0 {
+        match vault.user_count.checked_add(1) {
+            Some(result) => vault.user_count = result,
+            None => return err!(ErrorCode::Overflow),
+        }
+    }
+
+    user.waitlisted -= amount;
+    match user.amount.checked_add(amount) {
+        Some(result) => user.amount = result,
+        None => return err!(ErrorCode::Overflow),
+    }
+
+    Ok(())
+}
+
+// Tranches passed as remaining accounts, ordered from most senior
+fn load_tranches<'info>(
+    vault_count: u64,
+    vault: &state::Vault,
+    remaining_accounts: &'info [AccountInfo<'info>],
+) -> Result<Vec<Account<'info, Tranche>>> {
+    if remaining_accounts.len() != vault.tranche_count as usize {
+        return err!(ErrorCode::InvalidTranche);
+    }
+
+    let mut tranches = Vec::with_capacity(remaining_accounts.len());
+    for (index, account_info) in remaining_accounts.iter().enumerate() {
+        let tranche = Account::<Tranche>::try_from(account_info)?;
+        if tranche.vault_count != vault_count || tranche.index as usize != index {
+            return err!(ErrorCode::InvalidTranche);
+        }
+        tranches.push(tranche);
+    }
+
+    Ok(tranches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 500_000;
+
+    // Bullet vault of 10% over 1_000_000 ms, 400_000 of 1_000_000 collected
+    fn vault() -> state::Vault {
+        let data = vec![0u8; 8 + std::mem::size_of::<state::Vault>()];
+        let mut vault = state::Vault::try_deserialize_unchecked(&mut &data[..]).unwrap();
+        vault.yield_bps = 1_000;
+        vault.vault_capacity = 1_000_000;
+        vault.amount_collected = 400_000;
+        vault.end_date = 1_000_000;
+        vault
+    }
+
+    fn params(
+        yield_bps: Option<u16>,
+        vault_capacity: Option<u64>,
+        end_date: Option<u64>,
+    ) -> InitOrUpdateVaultParam {
+        let mut params = InitOrUpdateVaultParam::deserialize(&mut &[0u8; 64][..]).unwrap();
+        params.yield_bps = yield_bps;
+        params.vault_capacity = vault_capacity;
+        params.end_date = end_date;
+        params
+    }
+
+    fn not_updatable() -> Error {
+        error!(ErrorCode::NotUpdatable)
+    }
+
+    #[test]
+    fn yield_can_be_raised_without_approval() {
+        let mut vault = vault();
+        vault.repaid_at = NOW;
+
+        amend_vault(&mut vault, &params(Some(1_200), None, None), false, NOW).unwrap();
+        assert_eq!(vault.yield_bps, 1_200);
+        // The repayment no longer covers the raised yield
+        assert_eq!(vault.repaid_at, 0);
+    }
+
+    #[test]
+    fn yield_can_only_be_cut_with_approval() {
+        let mut vault = vault();
+        let params = params(Some(800), None, None);
+
+        assert_eq!(
+            amend_vault(&mut vault, &params, false, NOW).unwrap_err(),
+            not_updatable()
+        );
+        assert_eq!(vault.yield_bps, 1_000);
+
+        amend_vault(&mut vault, &params, true, NOW).unwrap();
+        assert_eq!(vault.yield_bps, 800);
+    }
+
+    #[test]
+    fn yield_is_frozen_once_coupons_or_instalments_are_paid() {
+        let mut coupon_vault = vault();
+        coupon_vault.coupons_paid = 1;
+        let mut amortizing_vault = vault();
+        amortizing_vault.instalments_repaid = 1;
+
+        for mut vault in [coupon_vault, amortizing_vault] {
+            for yield_bps in [800, 1_200] {
+                let params = params(Some(yield_bps), None, None);
+                assert_eq!(
+                    amend_vault(&mut vault, &params, true, NOW).unwrap_err(),
+                    not_updatable()
+                );
+            }
+            // Other amendments still go through
+            amend_vault(&mut vault, &params(None, Some(2_000_000), None), false, NOW).unwrap();
+            assert_eq!(vault.yield_bps, 1_000);
+        }
+    }
+
+    #[test]
+    fn capacity_can_be_raised_without_approval() {
+        let mut vault = vault();
+
+        amend_vault(&mut vault, &params(None, Some(2_000_000), None), false, NOW).unwrap();
+        assert_eq!(vault.vault_capacity, 2_000_000);
+    }
+
+    #[test]
+    fn capacity_can_only_be_cut_with_approval_down_to_the_amount_collected() {
+        let mut vault = vault();
+
+        assert_eq!(
+            amend_vault(&mut vault, &params(None, Some(400_000), None), false, NOW).unwrap_err(),
+            not_updatable()
+        );
+        assert_eq!(
+            amend_vault(&mut vault, &params(None, Some(399_999), None), true, NOW).unwrap_err(),
+            not_updatable()
+        );
+
+        amend_vault(&mut vault, &params(None, Some(400_000), None), true, NOW).unwrap();
+        assert_eq!(vault.vault_capacity, 400_000);
+    }
+
+    #[test]
+    fn capacity_is_frozen_once_allocated_or_split_into_tranches() {
+        let mut allocated_vault = vault();
+        allocated_vault.allocation_finalized = true;
+        let mut tranched_vault = vault();
+        tranched_vault.tranche_count = 2;
+
+        for mut vault in [allocated_vault, tranched_vault] {
+            for vault_capacity in [500_000, 2_000_000] {
+                let params = params(None, Some(vault_capacity), None);
+                assert_eq!(
+                    amend_vault(&mut vault, &params, true, NOW).unwrap_err(),
+                    not_updatable()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn term_can_be_shortened_without_approval_if_the_term_yield_holds() {
+        let mut vault = vault();
+        vault.repaid_at = NOW;
+
+        // Half the term at double the yield
+        let params = params(Some(2_000), None, Some(500_001));
+        amend_vault(&mut vault, &params, false, NOW).unwrap();
+        assert_eq!((vault.yield_bps, vault.end_date), (2_000, 500_001));
+        assert_eq!(vault.repaid_at, 0);
+    }
+
+    #[test]
+    fn term_yield_cut_requires_approval() {
+        let mut vault = vault();
+        let params = params(None, None, Some(900_000));
+
+        assert_eq!(
+            amend_vault(&mut vault, &params, false, NOW).unwrap_err(),
+            not_updatable()
+        );
+        assert_eq!(vault.end_date, 1_000_000);
+
+        amend_vault(&mut vault, &params, true, NOW).unwrap();
+        assert_eq!(vault.end_date, 900_000);
+    }
+
+    #[test]
+    fn term_can_only_be_extended_with_approval() {
+        let mut vault = vault();
+        let params = params(None, None, Some(2_000_000));
+
+        assert_eq!(
+            amend_vault(&mut vault, &params, false, NOW).unwrap_err(),
+            not_updatable()
+        );
+
+        amend_vault(&mut vault, &params, true, NOW).unwrap();
+        assert_eq!(vault.end_date, 2_000_000);
+    }
+
+    #[test]
+    fn term_must_end_in_the_future() {
+        let mut vault = vault();
+
+        assert_eq!(
+            amend_vault(&mut vault, &params(None, None, Some(NOW)), true, NOW).unwrap_err(),
+            error!(ErrorCode::InvalidEndTimeInput)
+        );
+    }
+
+    #[test]
+    fn term_is_frozen_for_coupon_and_instalment_schedules() {
+        let mut coupon_vault = vault();
+        coupon_vault.coupon_interval = 100_000;
+        let mut amortizing_vault = vault();
+        amortizing_vault.amortization = AmortizationStyle::EqualPrincipal;
+
+        for mut vault in [coupon_vault, amortizing_vault] {
+            for end_date in [900_000, 2_000_000] {
+                let params = params(None, None, Some(end_date));
+                assert_eq!(
+                    amend_vault(&mut vault, &params, true, NOW).unwrap_err(),
+                    not_updatable()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn approved_amendment_applies_every_change() {
+        let mut vault = vault();
+        let params = params(Some(500), Some(600_000), Some(2_000_000));
+
+        amend_vault(&mut vault, &params, true, NOW).unwrap();
+        assert_eq!(
+            (vault.yield_bps, vault.vault_capacity, vault.end_date),
+            (500, 600_000, 2_000_000)
+        );
+    }
+
+    #[test]
+    fn settled_vault_cannot_be_amended() {
+        let mut vault = vault();
+        vault.status = VaultStatus::Settled;
+
+        assert_eq!(
+            amend_vault(&mut vault, &params(Some(1_200), None, None), true, NOW).unwrap_err(),
+            not_updatable()
+        );
+    }
 }
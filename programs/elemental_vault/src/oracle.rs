@@ -0,0 +1,347 @@
+use anchor_lang::prelude::*;
+use solana_program::pubkey;
+
+use crate::error::ErrorCode;
+use crate::state::Vault;
+
+// Pyth oracle program on mainnet-beta and devnet
+pub const PYTH_PROGRAM_IDS: [Pubkey; 2] = [
+    pubkey!("FsJ3A3u2vn5cTVofAjvy6y5kwABJAqYWpe4975bi2epH"),
+    pubkey!("gSbePebfvPy7tRqimPoVecS2UsBvYv46ynrzWocc92s"),
+];
+
+// Pyth v2 price account layout
+const PYTH_MAGIC: u32 = 0xa1b2c3d4;
+const PYTH_ACCOUNT_TYPE_PRICE: u32 = 3;
+const PYTH_STATUS_TRADING: u32 = 1;
+const PYTH_MAGIC_OFFSET: usize = 0;
+const PYTH_ACCOUNT_TYPE_OFFSET: usize = 8;
+const PYTH_EXPO_OFFSET: usize = 20;
+const PYTH_TIMESTAMP_OFFSET: usize = 96;
+const PYTH_AGG_PRICE_OFFSET: usize = 208;
+const PYTH_AGG_CONF_OFFSET: usize = 216;
+const PYTH_AGG_STATUS_OFFSET: usize = 224;
+const PYTH_MIN_LEN: usize = 240;
+
+// Price of one collateral token in base tokens, as price * 10^expo
+#[derive(Debug)]
+pub struct Price {
+    pub price: i64,
+    pub conf: u64,
+    pub expo: i32,
+    // Publish time in milliseconds
+    pub publish_time: u64,
+}
+
+impl Price {
+    // Values collateral 1:1 against the base token
+    pub fn unit() -> Price {
+        Price {
+            price: 1,
+            conf: 0,
+            expo: 0,
+            publish_time: 0,
+        }
+    }
+
+    // Loads and validates the collateral price of a vault, None when it has no oracle
+    pub fn load_collateral_price(
+        vault: &Vault,
+        oracle: Option<&AccountInfo>,
+        now: u64,
+    ) -> Result<Option<Price>> {
+        if vault.collateral_oracle == Pubkey::default() {
+            return Ok(None);
+        }
+        let oracle = match oracle {
+            Some(oracle) if oracle.key() == vault.collateral_oracle => oracle,
+            _ => return err!(ErrorCode::InvalidOracle),
+        };
+
+        let price = Price::load(oracle)?;
+        price.validate(now, vault.oracle_max_staleness, vault.oracle_max_conf_bps)?;
+
+        Ok(Some(price))
+    }
+
+    // Reads a Pyth price account, or a manual price account set by the admin on localnet
+    pub fn load(account: &AccountInfo) -> Result<Price> {
+        #[cfg(feature = "localnet")]
+        if account.owner == &crate::ID {
+            let data = account.try_borrow_data()?;
+            let manual_price = crate::state::ManualPrice::try_deserialize(&mut &data[..])?;
+            return Ok(Price {
+                price: manual_price.price,
+                conf: manual_price.conf,
+                expo: manual_price.expo,
+                publish_time: manual_price.publish_time,
+            });
+        }
+        if !PYTH_PROGRAM_IDS.contains(account.owner) {
+            return err!(ErrorCode::InvalidOracle);
+        }
+
+        let data = account.try_borrow_data()?;
+        if data.len() < PYTH_MIN_LEN
+            || read_u32(&data, PYTH_MAGIC_OFFSET) != PYTH_MAGIC
+            || read_u32(&data, PYTH_ACCOUNT_TYPE_OFFSET) != PYTH_ACCOUNT_TYPE_PRICE
+        {
+            return err!(ErrorCode::InvalidOracle);
+        }
+        if read_u32(&data, PYTH_AGG_STATUS_OFFSET) != PYTH_STATUS_TRADING {
+            return err!(ErrorCode::OraclePriceUnavailable);
+        }
+
+        let timestamp = read_u64(&data, PYTH_TIMESTAMP_OFFSET) as i64;
+        Ok(Price {
+            price: read_u64(&data, PYTH_AGG_PRICE_OFFSET) as i64,
+            conf: read_u64(&data, PYTH_AGG_CONF_OFFSET),
+            expo: read_u32(&data, PYTH_EXPO_OFFSET) as i32,
+            publish_time: (timestamp.max(0) * 1000) as u64,
+        })
+    }
+
+    // Rejects prices older than max_staleness or with confidence wider than max_conf_bps
+    pub fn validate(&self, now: u64, max_staleness: u64, max_conf_bps: u16) -> Result<()> {
+        if self.price <= 0 {
+            return err!(ErrorCode::OraclePriceUnavailable);
+        }
+        if now.saturating_sub(self.publish_time) > max_staleness {
+            return err!(ErrorCode::OraclePriceStale);
+        }
+        if (self.conf as u128) * 10_000 > (self.price as u128) * (max_conf_bps as u128) {
+            return err!(ErrorCode::OracleConfidenceTooWide);
+        }
+
+        Ok(())
+    }
+
+    // Value of a collateral amount in base units, using the lower bound of the confidence interval
+    pub fn value(&self, amount: u64, collateral_decimals: u8, base_decimals: u8) -> Option<u64> {
+        let price = (self.price as u64).checked_sub(self.conf)? as u128;
+        let exponent = self.expo + base_decimals as i32 - collateral_decimals as i32;
+        let value = (amount as u128).checked_mul(price)?;
+        let value = if exponent >= 0 {
+            value.checked_mul(10u128.checked_pow(exponent as u32)?)?
+        } else {
+            value / 10u128.checked_pow(exponent.unsigned_abs())?
+        };
+        u64::try_from(value).ok()
+    }
+
+    // Collateral amount worth a base value, rounded up and priced like `value`
+    pub fn amount(&self, value: u64, collateral_decimals: u8, base_decimals: u8) -> Option<u64> {
+        let price = (self.price as u64).checked_sub(self.conf)? as u128;
+        if price == 0 {
+            return None;
+        }
+        let exponent = self.expo + base_decimals as i32 - collateral_decimals as i32;
+        let (numerator, denominator) = if exponent >= 0 {
+            (
+                value as u128,
+                price.checked_mul(10u128.checked_pow(exponent as u32)?)?,
+            )
+        } else {
+            (
+                (value as u128).checked_mul(10u128.checked_pow(exponent.unsigned_abs())?)?,
+                price,
+            )
+        };
+        u64::try_from(numerator.div_ceil(denominator)).ok()
+    }
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 150.0 +/- 0.1 published at 1_700_000_000 seconds
+    const PRICE: i64 = 15_000_000_000;
+    const CONF: u64 = 10_000_000;
+    const EXPO: i32 = -8;
+    const TIMESTAMP: i64 = 1_700_000_000;
+
+    fn pyth_account(status: u32) -> Vec<u8> {
+        let mut data = vec![0u8; PYTH_MIN_LEN];
+        data[PYTH_MAGIC_OFFSET..PYTH_MAGIC_OFFSET + 4].copy_from_slice(&PYTH_MAGIC.to_le_bytes());
+        data[PYTH_ACCOUNT_TYPE_OFFSET..PYTH_ACCOUNT_TYPE_OFFSET + 4]
+            .copy_from_slice(&PYTH_ACCOUNT_TYPE_PRICE.to_le_bytes());
+        data[PYTH_EXPO_OFFSET..PYTH_EXPO_OFFSET + 4].copy_from_slice(&EXPO.to_le_bytes());
+        data[PYTH_TIMESTAMP_OFFSET..PYTH_TIMESTAMP_OFFSET + 8]
+            .copy_from_slice(&TIMESTAMP.to_le_bytes());
+        data[PYTH_AGG_PRICE_OFFSET..PYTH_AGG_PRICE_OFFSET + 8]
+            .copy_from_slice(&PRICE.to_le_bytes());
+        data[PYTH_AGG_CONF_OFFSET..PYTH_AGG_CONF_OFFSET + 8].copy_from_slice(&CONF.to_le_bytes());
+        data[PYTH_AGG_STATUS_OFFSET..PYTH_AGG_STATUS_OFFSET + 4]
+            .copy_from_slice(&status.to_le_bytes());
+        data
+    }
+
+    fn load(data: &mut [u8], owner: &Pubkey) -> Result<Price> {
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let account = AccountInfo::new(&key, false, false, &mut lamports, data, owner, false, 0);
+        Price::load(&account)
+    }
+
+    fn price(price: i64, conf: u64, expo: i32) -> Price {
+        Price {
+            price,
+            conf,
+            expo,
+            publish_time: 0,
+        }
+    }
+
+    #[test]
+    fn load_reads_the_pyth_price_layout() {
+        for owner in PYTH_PROGRAM_IDS {
+            let price = load(&mut pyth_account(PYTH_STATUS_TRADING), &owner).unwrap();
+
+            assert_eq!(price.price, PRICE);
+            assert_eq!(price.conf, CONF);
+            assert_eq!(price.expo, EXPO);
+            assert_eq!(price.publish_time, TIMESTAMP as u64 * 1000);
+        }
+    }
+
+    #[test]
+    fn load_rejects_accounts_that_are_not_pyth_prices() {
+        let owner = PYTH_PROGRAM_IDS[0];
+
+        let mut data = pyth_account(PYTH_STATUS_TRADING);
+        assert_eq!(
+            load(&mut data, &Pubkey::new_unique()).unwrap_err(),
+            error!(ErrorCode::InvalidOracle)
+        );
+        assert_eq!(
+            load(&mut data[..PYTH_MIN_LEN - 1], &owner).unwrap_err(),
+            error!(ErrorCode::InvalidOracle)
+        );
+
+        let mut data = pyth_account(PYTH_STATUS_TRADING);
+        data[PYTH_MAGIC_OFFSET] ^= 1;
+        assert_eq!(
+            load(&mut data, &owner).unwrap_err(),
+            error!(ErrorCode::InvalidOracle)
+        );
+
+        let mut data = pyth_account(PYTH_STATUS_TRADING);
+        data[PYTH_ACCOUNT_TYPE_OFFSET] = 2;
+        assert_eq!(
+            load(&mut data, &owner).unwrap_err(),
+            error!(ErrorCode::InvalidOracle)
+        );
+    }
+
+    #[test]
+    fn load_rejects_prices_that_are_not_trading() {
+        assert_eq!(
+            load(&mut pyth_account(0), &PYTH_PROGRAM_IDS[0]).unwrap_err(),
+            error!(ErrorCode::OraclePriceUnavailable)
+        );
+    }
+
+    #[test]
+    fn validate_bounds_staleness() {
+        let price = Price {
+            publish_time: 1_000_000,
+            ..price(10_000, 0, 0)
+        };
+
+        assert!(price.validate(1_000_000, 60_000, 0).is_ok());
+        assert!(price.validate(1_060_000, 60_000, 0).is_ok());
+        assert_eq!(
+            price.validate(1_060_001, 60_000, 0).unwrap_err(),
+            error!(ErrorCode::OraclePriceStale)
+        );
+        // A publish time ahead of the clock is not stale
+        assert!(price.validate(0, 60_000, 0).is_ok());
+    }
+
+    #[test]
+    fn validate_bounds_confidence() {
+        // Confidence of 100 bps
+        let price = price(10_000, 100, 0);
+
+        assert!(price.validate(0, 0, 100).is_ok());
+        assert_eq!(
+            price.validate(0, 0, 99).unwrap_err(),
+            error!(ErrorCode::OracleConfidenceTooWide)
+        );
+    }
+
+    #[test]
+    fn validate_rejects_non_positive_prices() {
+        for value in [0, -1] {
+            assert_eq!(
+                price(value, 0, 0).validate(0, 0, 10_000).unwrap_err(),
+                error!(ErrorCode::OraclePriceUnavailable)
+            );
+        }
+    }
+
+    #[test]
+    fn value_scales_across_exponents_and_decimals() {
+        // 150.0 with a 9 decimal collateral and 6 decimal base
+        assert_eq!(
+            price(150_000, 0, -3).value(2_000_000_000, 9, 6),
+            Some(300_000_000)
+        );
+        // 300 with a positive exponent and equal decimals
+        assert_eq!(price(3, 0, 2).value(1_000_000, 6, 6), Some(300_000_000));
+        // 2.0 with a 6 decimal collateral and 9 decimal base
+        assert_eq!(price(2, 0, 0).value(1_000_000, 6, 9), Some(2_000_000_000));
+        // Values round down
+        assert_eq!(price(150_000, 0, -3).value(7, 9, 6), Some(1));
+    }
+
+    #[test]
+    fn value_uses_the_lower_confidence_bound() {
+        assert_eq!(
+            price(150_000, 1_000, -3).value(2_000_000_000, 9, 6),
+            Some(298_000_000)
+        );
+        assert_eq!(price(100, 100, 0).value(1, 6, 6), Some(0));
+        assert_eq!(price(100, 101, 0).value(1, 6, 6), None);
+    }
+
+    #[test]
+    fn value_rejects_results_that_overflow() {
+        assert_eq!(price(i64::MAX, 0, 0).value(u64::MAX, 6, 6), None);
+        assert_eq!(price(1, 0, 0).value(u64::MAX, 0, 18), None);
+    }
+
+    #[test]
+    fn amount_inverts_value_rounding_up() {
+        assert_eq!(
+            price(150_000, 0, -3).amount(300_000_000, 9, 6),
+            Some(2_000_000_000)
+        );
+        assert_eq!(price(3, 0, 2).amount(300_000_000, 6, 6), Some(1_000_000));
+        assert_eq!(price(2, 0, 0).amount(2_000_000_000, 6, 9), Some(1_000_000));
+
+        // Amounts round up so the collateral always covers the value
+        assert_eq!(price(150_000, 0, -3).amount(1, 9, 6), Some(7));
+        assert_eq!(price(3, 0, 2).amount(301, 6, 6), Some(2));
+        for value in [1, 999, 1_000_001] {
+            let price = price(150_000, 1_000, -3);
+            let amount = price.amount(value, 9, 6).unwrap();
+            assert!(price.value(amount, 9, 6).unwrap() >= value);
+        }
+    }
+
+    #[test]
+    fn amount_rejects_prices_without_a_lower_bound() {
+        assert_eq!(price(100, 100, 0).amount(1, 6, 6), None);
+        assert_eq!(price(100, 101, 0).amount(1, 6, 6), None);
+    }
+}
@@ -1,8 +1,17 @@
 use anchor_lang::prelude::*;
 
+use anchor_lang::solana_program::keccak;
+
+use crate::contexts::InitOrUpdateVaultParam;
+use crate::oracle::Price;
+
 #[account]
 pub struct Global {
     pub vault_counter: u64,
+    // Sets manual prices and registers attestors
+    pub admin: Pubkey,
+    // Keys allowed to issue KYC credentials
+    pub attestors: [Pubkey; MAX_ATTESTORS],
 }
 
 #[account]
@@ -33,6 +42,180 @@ pub struct Vault {
     pub amount_withdrawn: u64,
     // Total amount redeemed by users
     pub amount_redeemed: u64,
+    // Fixed-term or share-based perpetual accounting
+    pub mode: VaultMode,
+    // Source of total assets for share conversions
+    pub asset_source: AssetSource,
+    // Share mint for perpetual vaults
+    pub share_mint: Pubkey,
+    // Total assets last reported by authority
+    pub total_assets: u64,
+    // Total amount repaid by authority
+    pub amount_repaid: u64,
+    // Number of withdraw requests ever queued
+    pub queue_count: u64,
+    // Cumulative assets requested through the withdraw queue
+    pub queue_requested: u64,
+    // Cumulative queued assets filled by repayments
+    pub queue_filled: u64,
+    // Cumulative queued assets claimed by users
+    pub queue_claimed: u64,
+    // Allow users to exit between start_date and end_date
+    pub early_withdraw: bool,
+    // Penalty charged on early withdrawn principal
+    pub early_withdraw_penalty_bps: u16,
+    // Penalty decays linearly toward end_date
+    pub penalty_decay: bool,
+    // Receiver of early withdrawal penalties
+    pub penalty_recipient: PenaltyRecipient,
+    // Total principal withdrawn before end_date
+    pub amount_early_withdrawn: u64,
+    // Total penalty charged on early withdrawals
+    pub penalty_collected: u64,
+    // Penalty distributed per unit of remaining principal, scaled by PENALTY_SCALE
+    pub penalty_per_amount: u128,
+    // Payout vests linearly over this period after end_date
+    pub vesting_period: u64,
+    // Coupon schedule during the vault term
+    pub coupon_frequency: CouponFrequency,
+    // Time between coupon dates, 0 without coupons
+    pub coupon_interval: u64,
+    // Number of coupon dates between start_date and end_date
+    pub coupon_count: u64,
+    // Total coupons paid to users
+    pub coupons_paid: u64,
+    // Principal repayment schedule
+    pub amortization: AmortizationStyle,
+    // Number of instalments evenly spaced between start_date and end_date
+    pub instalment_count: u64,
+    // Number of instalments repaid by authority
+    pub instalments_repaid: u64,
+    // Number of tranches, 0 when all depositors rank equally
+    pub tranche_count: u8,
+    // Sum of the tranche capacities, must match vault_capacity before deposits
+    pub tranche_capacity: u64,
+    // Repayment status after end_date
+    pub status: VaultStatus,
+    // Total owed to users when the vault was settled
+    pub settled_owed: u64,
+    // Total recovered for users when the vault was settled
+    pub settled_recovered: u64,
+    // Number of open User accounts
+    pub user_count: u64,
+    // First-loss capital held for depositors
+    pub first_loss_amount: u64,
+    // First-loss capital used to top up redemptions
+    pub first_loss_used: u64,
+    // Mint of the collateral locked by the authority
+    pub collateral_mint: Pubkey,
+    // Decimals of the collateral mint
+    pub collateral_decimals: u8,
+    // Collateral required against vault capacity, 0 for unsecured vaults
+    pub collateral_ratio_bps: u16,
+    // Total collateral locked by the authority
+    pub collateral_deposited: u64,
+    // Total collateral claimed by users after a default
+    pub collateral_claimed: u64,
+    // Price account valuing the collateral in base tokens, default for 1:1
+    pub collateral_oracle: Pubkey,
+    // Maximum age of the oracle price in milliseconds
+    pub oracle_max_staleness: u64,
+    // Maximum oracle confidence interval relative to the price
+    pub oracle_max_conf_bps: u16,
+    // Collateral ratio against principal below which the vault can be liquidated
+    pub maintenance_ratio_bps: u16,
+    // Share of the seized collateral paid to the liquidator
+    pub liquidation_bounty_bps: u16,
+    // Deposits require the maximum yield to be prefunded
+    pub require_yield_reserve: bool,
+    // Yield prefunded by the authority into the vault ATA
+    pub yield_reserve: u64,
+    // Time after end_date before late penalty interest accrues
+    pub grace_period: u64,
+    // Penalty interest per day of late repayment
+    pub late_penalty_bps: u16,
+    // Days of late penalty interest, frozen at settlement
+    pub late_days: u64,
+    // Amount redeemed by users before settlement
+    pub redeemed_at_settlement: u64,
+    // Merkle root of allowed wallets and their deposit caps, zero for an open vault
+    pub allowlist_root: [u8; 32],
+    // Attestor whose credential is required to deposit, default for none
+    pub kyc_issuer: Pubkey,
+    // Maximum cumulative deposit per wallet, 0 for no cap
+    pub max_per_user: u64,
+    // Per-wallet cap overrides by credential level, 0 to use max_per_user
+    pub tier_caps: [u64; MAX_TIERS],
+    // Deposits beyond capacity are allocated pro-rata once deposits close
+    pub oversubscription: bool,
+    // Total committed before allocation
+    pub amount_committed: u64,
+    // Whether the allocation was finalized after deposits closed
+    pub allocation_finalized: bool,
+    // Users still to claim their excess commitment
+    pub users_pending: u64,
+    // Next waitlist entry id
+    pub waitlist_count: u64,
+    // Next waitlist entry to promote
+    pub waitlist_head: u64,
+    // Deposits escrowed in the vault ATA by the waitlist
+    pub waitlist_amount: u64,
+    // Time deposits open, 0 to open on creation
+    pub deposit_open: u64,
+    // Time deposits close, 0 to close at start_date
+    pub deposit_close: u64,
+    // Proposed end date of a pending maturity extension, 0 for none
+    pub extension_end_date: u64,
+    // Proposed yield of a pending maturity extension
+    pub extension_yield_bps: u16,
+    // Time until which users can opt out of the pending extension
+    pub opt_out_deadline: u64,
+    // End date before the extension was applied, 0 if never extended
+    pub original_end_date: u64,
+    // Yield before the extension was applied
+    pub original_yield_bps: u16,
+    // Principal of the users who opted out of the extension
+    pub amount_opted_out: u64,
+    // Principal redeemed by opted-out users before settlement
+    pub opt_out_redeemed: u64,
+    // Amount paid to opted-out users
+    pub opt_out_paid: u64,
+    // Coupons paid to users who have since withdrawn early
+    pub coupons_released: u64,
+    // Time the vault ATA first covered everything owed, 0 until repaid
+    pub repaid_at: u64,
+    // Share of the outstanding principal that must vote on an amendment, 0 without governance
+    pub governance_quorum_bps: u16,
+    // Share of the votes cast that must approve an amendment
+    pub governance_threshold_bps: u16,
+    // Duration of the vote on an amendment
+    pub voting_period: u64,
+    // Next proposal id
+    pub proposal_count: u64,
+}
+#[account]
+pub struct Credential {
+    // Attestor that issued the credential
+    pub issuer: Pubkey,
+    // Wallet the credential was issued to
+    pub wallet: Pubkey,
+    // Expiry of the credential in milliseconds
+    pub expiry: u64,
+    // ISO 3166 country code of the wallet owner
+    pub jurisdiction: [u8; 2],
+    // Verification level, selects the vault tier cap
+    pub level: u8,
+}
+#[account]
+pub struct ManualPrice {
+    // Price of one collateral token in base tokens, as price * 10^expo
+    pub price: i64,
+    // Confidence interval of the price
+    pub conf: u64,
+    // Price exponent
+    pub expo: i32,
+    // Time the price was set in milliseconds
+    pub publish_time: u64,
 }
 #[account]
 pub struct User {
@@ -42,12 +225,209 @@ pub struct User {
     pub owner: Pubkey,
     // Total amount deposited by user
     pub amount: u64,
+    // Total payout claimed by user
+    pub amount_claimed: u64,
+    // Last coupon period claimed by user
+    pub last_coupon_period: u64,
+    // Number of instalments claimed by user
+    pub instalments_claimed: u64,
+    // Tranche the user deposited into
+    pub tranche: u8,
+    // Whether the user claimed their share of the collateral
+    pub collateral_claimed: bool,
+    // Whether the user's commitment was allocated in an oversubscribed vault
+    pub allocated: bool,
+    // Whether the user keeps the terms before a maturity extension
+    pub opted_out: bool,
+    // End of the latest vote cast by the user, exits are blocked until then
+    pub vote_locked_until: u64,
+    // Coupons paid to the user
+    pub coupons_claimed: u64,
+    // Deposits of the user still escrowed on the waitlist
+    pub waitlisted: u64,
+}
+
+#[account]
+pub struct Tranche {
+    // Vault count for Vault reference
+    pub vault_count: u64,
+    // Seniority, 0 is the most senior tranche
+    pub index: u8,
+    // Tranche Yield
+    pub yield_bps: u16,
+    // Capacity of tranche
+    pub vault_capacity: u64,
+    // Minimum deposit per user
+    pub min_amount: u64,
+    // Total amount collected
+    pub amount_collected: u64,
+    // Total principal withdrawn before end_date
+    pub amount_early_withdrawn: u64,
+    // Total penalty charged on early withdrawals
+    pub penalty_collected: u64,
+    // Total amount redeemed by users
+    pub amount_redeemed: u64,
+    // Total owed to users when the vault was settled
+    pub settled_owed: u64,
+    // Total recovered for users when the vault was settled
+    pub settled_recovered: u64,
+}
+
+#[account]
+pub struct WithdrawRequest {
+    // Vault count for Vault reference
+    pub vault_count: u64,
+    // Position in the withdraw queue
+    pub request_id: u64,
+    // User pubkey
+    pub owner: Pubkey,
+    // Assets owed for the burned shares
+    pub assets: u64,
+    // Filled once queue_filled reaches this amount
+    pub cumulative_end: u64,
+}
+
+#[account]
+pub struct Proposal {
+    // Vault count for Vault reference
+    pub vault_count: u64,
+    // Position among the vault proposals
+    pub proposal_id: u64,
+    // Amendment applied once the proposal passes
+    pub params: InitOrUpdateVaultParam,
+    // Deposits voting in favour
+    pub votes_for: u64,
+    // Deposits voting against
+    pub votes_against: u64,
+    // Time voting ends
+    pub voting_end: u64,
+    // Outstanding principal when the proposal was created
+    pub total_weight: u64,
+    // Whether the amendment was applied
+    pub executed: bool,
+}
+
+#[account]
+pub struct Vote {
+    // Vault count for Vault reference
+    pub vault_count: u64,
+    // Proposal voted on
+    pub proposal_id: u64,
+    // User pubkey
+    pub owner: Pubkey,
+    // User deposit at the time of the vote
+    pub weight: u64,
+    // Whether the user approved the amendment
+    pub approve: bool,
+}
+
+#[account]
+pub struct WaitlistEntry {
+    // Vault count for Vault reference
+    pub vault_count: u64,
+    // Position in the waitlist
+    pub entry_id: u64,
+    // User pubkey
+    pub owner: Pubkey,
+    // Deposit escrowed in the vault ATA
+    pub amount: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum VaultMode {
+    // Raw token amounts paid out with a fixed yield at end_date
+    FixedTerm,
+    // ERC-4626 style shares redeemable at any time
+    Perpetual,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum AssetSource {
+    // Total assets equal the vault ATA balance
+    Balance,
+    // Total assets are reported by the authority
+    Reported,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum PenaltyRecipient {
+    // Penalty is shared pro-rata by the remaining depositors
+    Depositors,
+    // Penalty is left in the vault for the authority
+    Authority,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum CouponFrequency {
+    // Yield is only paid at end_date
+    None,
+    Monthly,
+    Quarterly,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum AmortizationStyle {
+    // Principal repaid in full at end_date
+    Bullet,
+    // Same principal every instalment, interest on outstanding principal
+    EqualPrincipal,
+    // Same principal and interest payment every instalment
+    Annuity,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum VaultStatus {
+    // Accepting deposits or running its term
+    Active,
+    // Vault held everything owed to users at settlement
+    Settled,
+    // Vault fell short at settlement, users recover pro-rata
+    Defaulted,
+    // Collateral fell below the maintenance ratio and was seized for users
+    Liquidating,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+    Down,
+    Up,
+}
+
+pub const PENALTY_SCALE: u128 = 1_000_000_000_000;
+pub const RATE_SCALE: u128 = 1_000_000_000_000;
+pub const MAX_INSTALMENTS: u64 = 120;
+pub const MAX_TRANCHES: u8 = 4;
+pub const DAY_MS: u64 = 86_400_000;
+pub const MAX_ATTESTORS: usize = 8;
+pub const MAX_TIERS: usize = 4;
+
+impl CouponFrequency {
+    pub fn interval(&self) -> u64 {
+        match self {
+            CouponFrequency::None => 0,
+            CouponFrequency::Monthly => 31_536_000_000 / 12,
+            CouponFrequency::Quarterly => 31_536_000_000 / 4,
+        }
+    }
 }
 
 impl Global {
     pub fn seed<'s>() -> &'s [u8] {
         b"global"
     }
+
+    pub fn is_attestor(&self, key: &Pubkey) -> bool {
+        *key != Pubkey::default() && self.attestors.contains(key)
+    }
+}
+impl Credential {
+    pub fn seed<'s>() -> &'s [u8] {
+        b"credential"
+    }
+
+    pub fn is_valid(&self, now: u64) -> bool {
+        now < self.expiry
+    }
 }
 impl Vault {
     pub fn seed<'s>() -> &'s [u8] {
@@ -55,18 +435,645 @@ impl Vault {
     }
 
     pub fn calculate_payout(base_amount: &u64, vault: &Vault) -> u64 {
-        // Calculate the commission amount using integer math, considering decimal places.
         let duration_seconds = vault.end_date - vault.start_date;
-        let yield_earned_per_year = (base_amount * vault.yield_bps as u64) / 10_000;
-        let yield_earned = (yield_earned_per_year * duration_seconds) / (31_536_000);
 
-        base_amount + yield_earned
+        base_amount + Vault::calculate_yield(base_amount, vault, duration_seconds)
+    }
+
+    pub fn calculate_yield(base_amount: &u64, vault: &Vault, duration_seconds: u64) -> u64 {
+        calculate_yield(base_amount, vault.yield_bps, duration_seconds)
+    }
+
+    // Amount still owed to users, net of everything already paid
+    pub fn outstanding_owed(&self) -> u64 {
+        let owed = calculate_owed(
+            self,
+            self.yield_bps,
+            Vault::extended_principal(self),
+            self.amount_redeemed
+                - (self.amount_early_withdrawn - self.penalty_collected)
+                - self.coupons_released
+                - self.opt_out_paid,
+        );
+
+        owed + Vault::calculate_opt_out_payout(&self.amount_opted_out, self)
+            .saturating_sub(self.opt_out_paid)
+    }
+
+    // Payout at the terms before the extension, for users who opted out
+    pub fn calculate_opt_out_payout(base_amount: &u64, vault: &Vault) -> u64 {
+        let (end_date, yield_bps) = match vault.original_end_date {
+            0 => (vault.end_date, vault.yield_bps),
+            original_end_date => (original_end_date, vault.original_yield_bps),
+        };
+
+        base_amount + calculate_yield(base_amount, yield_bps, end_date - vault.start_date)
+    }
+
+    // Principal of the users on the current terms
+    pub fn extended_principal(vault: &Vault) -> u64 {
+        vault.amount_collected - vault.amount_early_withdrawn - vault.amount_opted_out
+    }
+
+    pub fn extension_pending(vault: &Vault) -> bool {
+        vault.extension_end_date != 0
+    }
+
+    // Full days of late repayment after the grace period, until the vault was repaid
+    pub fn late_days(vault: &Vault, now: u64) -> u64 {
+        if vault.late_penalty_bps == 0 {
+            return 0;
+        }
+        let repaid_at = match vault.repaid_at {
+            0 => now,
+            repaid_at => repaid_at.min(now),
+        };
+        repaid_at.saturating_sub(vault.end_date + vault.grace_period) / DAY_MS
+    }
+
+    // Everything owed at now, including the late penalty accrued so far
+    pub fn liabilities_at(&self, tranches: &[Account<Tranche>], now: u64) -> u64 {
+        let mut vault = self.clone();
+        vault.late_days = Vault::late_days(self, now);
+        if tranches.is_empty() {
+            return vault.outstanding_owed();
+        }
+        tranches
+            .iter()
+            .map(|tranche| tranche.outstanding_owed(&vault))
+            .sum()
+    }
+
+    pub fn calculate_late_penalty(base_amount: &u64, vault: &Vault) -> u64 {
+        (base_amount * vault.late_penalty_bps as u64 * vault.late_days) / 10_000
+    }
+
+    // Snapshot what is owed against assets at now, paying senior tranches first
+    pub fn settle<'a>(
+        &mut self,
+        assets: u64,
+        tranches: impl Iterator<Item = &'a mut Tranche>,
+        now: u64,
+    ) {
+        self.late_days = Vault::late_days(self, now);
+
+        let mut remaining = assets;
+        let mut owed = 0;
+        let mut recovered = 0;
+        let mut has_tranche = false;
+        for tranche in tranches {
+            has_tranche = true;
+            tranche.settled_owed = tranche.outstanding_owed(self);
+            tranche.settled_recovered = tranche.settled_owed.min(remaining);
+            remaining -= tranche.settled_recovered;
+            owed += tranche.settled_owed;
+            recovered += tranche.settled_recovered;
+        }
+        if !has_tranche {
+            owed = self.outstanding_owed();
+            recovered = owed.min(remaining);
+        }
+
+        self.settled_owed = owed;
+        self.settled_recovered = recovered;
+        self.redeemed_at_settlement = self.amount_redeemed;
+        self.status = if recovered == owed {
+            VaultStatus::Settled
+        } else {
+            VaultStatus::Defaulted
+        };
+    }
+
+    // Payout owed to a user, scaled down pro-rata once the vault has defaulted
+    pub fn calculate_user_payout(user: &User, vault: &Vault, tranche: Option<&Tranche>) -> u64 {
+        let payout = Vault::calculate_user_owed(user, vault, tranche);
+
+        let (owed, recovered) = match tranche {
+            Some(tranche) => (tranche.settled_owed, tranche.settled_recovered),
+            None => (vault.settled_owed, vault.settled_recovered),
+        };
+        if vault.status != VaultStatus::Defaulted || owed == 0 {
+            return payout;
+        }
+
+        ((payout as u128) * (recovered as u128) / (owed as u128)) as u64
+    }
+
+    // Payout owed to a user before any shortfall
+    pub fn calculate_user_owed(user: &User, vault: &Vault, tranche: Option<&Tranche>) -> u64 {
+        let base_payout = match tranche {
+            Some(tranche) => Tranche::calculate_payout(&user.amount, vault, tranche),
+            None => Vault::calculate_payout(&user.amount, vault),
+        };
+        match user.opted_out {
+            true => Vault::calculate_opt_out_payout(&user.amount, vault),
+            false => {
+                base_payout
+                    + Vault::penalty_share(&user.amount, vault)
+                    + Vault::calculate_late_penalty(&user.amount, vault)
+                    - Vault::calculate_coupons(&user.amount, vault, user.last_coupon_period)
+            }
+        }
+    }
+
+    // Coupon periods elapsed at now, capped at the number of coupon dates
+    pub fn coupon_periods_elapsed(vault: &Vault, now: u64) -> u64 {
+        if vault.coupon_interval == 0 {
+            return 0;
+        }
+        let elapsed = now.saturating_sub(vault.start_date) / vault.coupon_interval;
+
+        elapsed.min(vault.coupon_count)
+    }
+
+    // Yield paid as coupons for the first `periods` coupon dates
+    pub fn calculate_coupons(base_amount: &u64, vault: &Vault, periods: u64) -> u64 {
+        Vault::calculate_yield(base_amount, vault, periods * vault.coupon_interval)
+    }
+
+    pub fn calculate_early_penalty(base_amount: &u64, vault: &Vault, now: u64) -> u64 {
+        let mut penalty_bps = vault.early_withdraw_penalty_bps as u64;
+        if vault.penalty_decay {
+            // Penalty shrinks linearly to zero at end_date
+            let remaining = vault.end_date.saturating_sub(now);
+            let duration = vault.end_date - vault.start_date;
+            penalty_bps = penalty_bps * remaining / duration;
+        }
+
+        base_amount * penalty_bps / 10_000
+    }
+
+    // Penalties redistributed to a depositor who stays until end_date
+    pub fn penalty_share(base_amount: &u64, vault: &Vault) -> u64 {
+        ((*base_amount as u128) * vault.penalty_per_amount / PENALTY_SCALE) as u64
+    }
+
+    // Portion of the payout vested at now, the whole payout without a vesting period
+    pub fn calculate_vested(payout: u64, vault: &Vault, now: u64) -> u64 {
+        if vault.vesting_period == 0 {
+            return payout;
+        }
+        let elapsed = now.saturating_sub(vault.end_date).min(vault.vesting_period);
+
+        ((payout as u128) * (elapsed as u128) / (vault.vesting_period as u128)) as u64
+    }
+
+    // Interest rate per instalment period, scaled by RATE_SCALE
+    pub fn instalment_rate(vault: &Vault) -> u128 {
+        let period = (vault.end_date - vault.start_date) / vault.instalment_count;
+
+        (vault.yield_bps as u128) * (period as u128) * RATE_SCALE / (10_000 * 31_536_000)
+    }
+
+    // Principal and interest due on instalments [from, to) of the schedule for principal
+    pub fn calculate_instalments(principal: u64, vault: &Vault, from: u64, to: u64) -> Option<u64> {
+        let count = vault.instalment_count;
+        let rate = Vault::instalment_rate(vault);
+        let principal = principal as u128;
+
+        let payment = match vault.amortization {
+            AmortizationStyle::Annuity if rate > 0 => {
+                // payment = principal * rate * growth / (growth - 1), growth = (1 + rate) ^ count
+                let mut growth = RATE_SCALE;
+                for _ in 0..count {
+                    growth = growth.checked_mul(RATE_SCALE + rate)? / RATE_SCALE;
+                }
+                let ratio = growth.checked_mul(RATE_SCALE)? / (growth - RATE_SCALE);
+                (principal.checked_mul(rate)? / RATE_SCALE).checked_mul(ratio)? / RATE_SCALE
+            }
+            _ => principal / (count as u128),
+        };
+
+        let mut outstanding = principal;
+        let mut due: u128 = 0;
+        for instalment in 0..to.min(count) {
+            let interest = outstanding.checked_mul(rate)? / RATE_SCALE;
+            let repaid = if instalment == count - 1 {
+                outstanding
+            } else {
+                match vault.amortization {
+                    AmortizationStyle::Annuity => payment.saturating_sub(interest).min(outstanding),
+                    _ => payment,
+                }
+            };
+            if instalment >= from {
+                due = due.checked_add(repaid + interest)?;
+            }
+            outstanding -= repaid;
+        }
+
+        u64::try_from(due).ok()
+    }
+
+    // Share of the instalments repaid since the user last claimed
+    pub fn calculate_user_instalments(user: &User, vault: &Vault) -> Option<u64> {
+        let due = Vault::calculate_instalments(
+            vault.amount_collected,
+            vault,
+            user.instalments_claimed,
+            vault.instalments_repaid,
+        )?;
+        mul_div(
+            due,
+            user.amount as u128,
+            vault.amount_collected as u128,
+            Rounding::Down,
+        )
+    }
+
+    // Instalments repaid but not yet claimed, reserved until the final instalment settles the vault
+    pub fn unclaimed_instalments(vault: &Vault) -> u64 {
+        if vault.amortization == AmortizationStyle::Bullet || vault.status != VaultStatus::Active {
+            return 0;
+        }
+        Vault::calculate_instalments(vault.amount_collected, vault, 0, vault.instalments_repaid)
+            .unwrap_or(u64::MAX)
+            .saturating_sub(vault.amount_redeemed)
+    }
+
+    pub fn first_loss_seed<'s>() -> &'s [u8] {
+        b"first_loss"
+    }
+
+    pub fn collateral_seed<'s>() -> &'s [u8] {
+        b"collateral"
+    }
+
+    // Collateral required against the vault capacity, in base units
+    pub fn collateral_required(vault: &Vault) -> Option<u64> {
+        mul_div(
+            vault.vault_capacity,
+            vault.collateral_ratio_bps as u128,
+            10_000,
+            Rounding::Up,
+        )
+    }
+
+    // Value of the locked collateral in base units, 1:1 after decimal normalization without an oracle
+    pub fn collateral_value(
+        vault: &Vault,
+        price: Option<&Price>,
+        base_decimals: u8,
+    ) -> Option<u64> {
+        let amount = vault.collateral_deposited - vault.collateral_claimed;
+        match price {
+            Some(price) => price.value(amount, vault.collateral_decimals, base_decimals),
+            None => Price::unit().value(amount, vault.collateral_decimals, base_decimals),
+        }
+    }
+
+    pub fn is_collateralized(vault: &Vault, price: Option<&Price>, base_decimals: u8) -> bool {
+        if vault.collateral_ratio_bps == 0 {
+            return true;
+        }
+        match (
+            Vault::collateral_required(vault),
+            Vault::collateral_value(vault, price, base_decimals),
+        ) {
+            (Some(required), Some(value)) => vault.collateral_deposited > 0 && value >= required,
+            _ => false,
+        }
+    }
+
+    // Maximum yield payable on the full vault capacity
+    pub fn yield_reserve_required(vault: &Vault) -> u64 {
+        Vault::calculate_payout(&vault.vault_capacity, vault) - vault.vault_capacity
+    }
+
+    // Reserve the authority cannot withdraw until settlement, released for unfilled capacity once deposits close
+    pub fn locked_yield_reserve(vault: &Vault, now: u64) -> u64 {
+        if vault.status != VaultStatus::Active && vault.status != VaultStatus::Liquidating {
+            return 0;
+        }
+        if now < Vault::deposit_close(vault) {
+            return vault.yield_reserve;
+        }
+        let principal = Vault::outstanding_principal(vault);
+        let owed_yield = Vault::calculate_payout(&principal, vault) - principal;
+
+        owed_yield.min(vault.yield_reserve)
+    }
+
+    // Verifies a wallet and its deposit cap against the allowlist root
+    pub fn is_allowlisted(vault: &Vault, owner: &Pubkey, cap: u64, proof: &[[u8; 32]]) -> bool {
+        let mut node = keccak::hashv(&[owner.as_ref(), &cap.to_le_bytes()]).0;
+        for sibling in proof {
+            // Pairs are hashed in sorted order so proofs need no direction flags
+            node = if node <= *sibling {
+                keccak::hashv(&[&node, sibling]).0
+            } else {
+                keccak::hashv(&[sibling, &node]).0
+            };
+        }
+
+        node == vault.allowlist_root
+    }
+
+    // Per-wallet cap from the allowlist leaf, then the credential tier, then the vault default
+    pub fn user_cap(vault: &Vault, leaf_cap: u64, credential_level: Option<u8>) -> u64 {
+        if leaf_cap != 0 {
+            return leaf_cap;
+        }
+        if let Some(level) = credential_level {
+            let tier_cap = vault.tier_caps[level as usize];
+            if tier_cap != 0 {
+                return tier_cap;
+            }
+        }
+
+        vault.max_per_user
+    }
+
+    pub fn deposit_close(vault: &Vault) -> u64 {
+        match vault.deposit_close {
+            0 => vault.start_date,
+            deposit_close => deposit_close,
+        }
+    }
+
+    // Deposit window must open before it closes, and close no later than start_date
+    pub fn is_valid_deposit_window(vault: &Vault) -> bool {
+        let deposit_close = Vault::deposit_close(vault);
+        vault.deposit_open < deposit_close && deposit_close <= vault.start_date
+    }
+
+    // Users deposit their full commitment unless the vault is oversubscribed
+    pub fn is_allocated(user: &User, vault: &Vault) -> bool {
+        !vault.oversubscription
+            || (vault.allocation_finalized
+                && (vault.amount_committed <= vault.vault_capacity || user.allocated))
+    }
+
+    // Some commitments still wait for allocation
+    pub fn allocation_pending(vault: &Vault) -> bool {
+        vault.oversubscription && (!vault.allocation_finalized || vault.users_pending > 0)
+    }
+
+    pub fn calculate_allocation(user: &User, vault: &Vault) -> u64 {
+        if vault.amount_committed <= vault.vault_capacity {
+            return user.amount;
+        }
+        mul_div(
+            user.amount,
+            vault.vault_capacity as u128,
+            vault.amount_committed as u128,
+            Rounding::Down,
+        )
+        .unwrap_or(0)
+    }
+
+    // Upper bound of the excess still to refund, including one unit of rounding per pending user
+    pub fn pending_excess(vault: &Vault) -> u64 {
+        if !vault.oversubscription || vault.amount_committed <= vault.vault_capacity {
+            return 0;
+        }
+        let pending_users = match vault.allocation_finalized {
+            true => vault.users_pending,
+            false => vault.user_count,
+        };
+        if pending_users == 0 {
+            return 0;
+        }
+
+        vault.amount_collected.saturating_sub(vault.vault_capacity) + pending_users
+    }
+
+    // Waitlist is only offered for vaults without per-wallet gating, tranches or oversubscription
+    pub fn has_waitlist(vault: &Vault) -> bool {
+        vault.mode == VaultMode::FixedTerm
+            && vault.tranche_count == 0
+            && !vault.oversubscription
+            && vault.allowlist_root == [0; 32]
+            && vault.kyc_issuer == Pubkey::default()
+    }
+
+    // Principal of the users still in the vault
+    pub fn outstanding_principal(vault: &Vault) -> u64 {
+        vault.amount_collected - vault.amount_early_withdrawn - vault.opt_out_redeemed
+    }
+
+    // Collateral value has fallen below the maintenance ratio of the outstanding principal
+    pub fn is_liquidatable(vault: &Vault, price: Option<&Price>, base_decimals: u8) -> bool {
+        let principal = Vault::outstanding_principal(vault);
+        if vault.maintenance_ratio_bps == 0 || principal == 0 {
+            return false;
+        }
+        let maintenance = mul_div(
+            principal,
+            vault.maintenance_ratio_bps as u128,
+            10_000,
+            Rounding::Up,
+        );
+        match (
+            maintenance,
+            Vault::collateral_value(vault, price, base_decimals),
+        ) {
+            (Some(maintenance), Some(value)) => value < maintenance,
+            _ => false,
+        }
+    }
+
+    // Share of the collateral pro-rata to the principal still held in the vault,
+    // capped at the collateral worth the user's shortfall at the oracle price
+    pub fn calculate_user_collateral(
+        user: &User,
+        vault: &Vault,
+        tranche: Option<&Tranche>,
+        price: Option<&Price>,
+        base_decimals: u8,
+    ) -> Option<u64> {
+        let principal = Vault::outstanding_principal(vault);
+        if principal == 0 {
+            return Some(0);
+        }
+        let share = mul_div(
+            vault.collateral_deposited,
+            user.amount as u128,
+            principal as u128,
+            Rounding::Down,
+        )?;
+
+        let shortfall = Vault::calculate_user_owed(user, vault, tranche)
+            - Vault::calculate_user_payout(user, vault, tranche);
+        let covered = match price {
+            Some(price) => price.amount(shortfall, vault.collateral_decimals, base_decimals),
+            None => Price::unit().amount(shortfall, vault.collateral_decimals, base_decimals),
+        }?;
+
+        Some(share.min(covered))
+    }
+
+    // Whether the user still has collateral to claim after a default
+    pub fn collateral_outstanding(user: &User, vault: &Vault) -> bool {
+        vault.status == VaultStatus::Defaulted
+            && vault.collateral_deposited > 0
+            && !user.collateral_claimed
+    }
+
+    pub fn share_seed<'s>() -> &'s [u8] {
+        b"share_mint"
+    }
+
+    // Funds lent out by the authority still count as assets until repaid
+    pub fn total_assets(&self, vault_ata_amount: u64) -> u64 {
+        match self.asset_source {
+            AssetSource::Balance => (vault_ata_amount
+                + self.amount_withdrawn.saturating_sub(self.amount_repaid))
+            .saturating_sub(self.queue_requested - self.queue_claimed),
+            AssetSource::Reported => self.total_assets,
+        }
+    }
+
+    // Payouts still claimable by users once the vault is settled
+    pub fn settled_reserve(&self) -> u64 {
+        match self.status {
+            VaultStatus::Settled | VaultStatus::Defaulted => self
+                .settled_recovered
+                .saturating_sub(self.amount_redeemed - self.redeemed_at_settlement),
+            _ => 0,
+        }
+    }
+
+    // Balance set aside for queued withdrawals that are filled but not yet claimed
+    pub fn reserved_balance(&self) -> u64 {
+        self.queue_filled - self.queue_claimed
+    }
+
+    // Liquidity available without jumping ahead of queued withdrawals
+    pub fn available_liquidity(&self, vault_ata_amount: u64) -> u64 {
+        vault_ata_amount.saturating_sub(self.queue_requested - self.queue_claimed)
+    }
+
+    // Fill queued requests in order with liquidity not already reserved
+    pub fn fill_queue(&mut self, vault_ata_amount: u64) -> u64 {
+        let free = vault_ata_amount.saturating_sub(self.reserved_balance());
+        let fill = free.min(self.queue_requested - self.queue_filled);
+        self.queue_filled += fill;
+        fill
+    }
+
+    // Virtual share and asset of 1 keeps the first depositor from inflating the share price.
+    pub fn convert_to_shares(
+        assets: u64,
+        total_assets: u64,
+        total_shares: u64,
+        rounding: Rounding,
+    ) -> Option<u64> {
+        mul_div(
+            assets,
+            (total_shares as u128) + 1,
+            (total_assets as u128) + 1,
+            rounding,
+        )
+    }
+
+    pub fn convert_to_assets(
+        shares: u64,
+        total_assets: u64,
+        total_shares: u64,
+        rounding: Rounding,
+    ) -> Option<u64> {
+        mul_div(
+            shares,
+            (total_assets as u128) + 1,
+            (total_shares as u128) + 1,
+            rounding,
+        )
+    }
+}
+
+fn calculate_yield(base_amount: &u64, yield_bps: u16, duration_seconds: u64) -> u64 {
+    // Calculate the commission amount using integer math, considering decimal places.
+    let yield_earned_per_year = (base_amount * yield_bps as u64) / 10_000;
+
+    (yield_earned_per_year * duration_seconds) / (31_536_000)
+}
+
+fn calculate_owed(vault: &Vault, yield_bps: u16, remaining_amount: u64, amount_paid: u64) -> u64 {
+    let duration_seconds = vault.end_date - vault.start_date;
+    let payout = remaining_amount
+        + calculate_yield(&remaining_amount, yield_bps, duration_seconds)
+        + Vault::penalty_share(&remaining_amount, vault)
+        + Vault::calculate_late_penalty(&remaining_amount, vault);
+
+    payout.saturating_sub(amount_paid)
+}
+
+fn mul_div(value: u64, numerator: u128, denominator: u128, rounding: Rounding) -> Option<u64> {
+    let product = (value as u128).checked_mul(numerator)?;
+    let result = match rounding {
+        Rounding::Down => product / denominator,
+        Rounding::Up => product.checked_add(denominator - 1)? / denominator,
+    };
+    u64::try_from(result).ok()
+}
+impl ManualPrice {
+    pub fn seed<'s>() -> &'s [u8] {
+        b"manual_price"
+    }
+}
+impl Proposal {
+    pub fn seed<'s>() -> &'s [u8] {
+        b"proposal"
+    }
+
+    // Voting has ended with the quorum of the principal voting and the threshold of the votes in favour
+    pub fn has_passed(&self, vault: &Vault, now: u64) -> bool {
+        if now < self.voting_end {
+            return false;
+        }
+        let votes_for = self.votes_for as u128;
+        let votes_cast = votes_for + self.votes_against as u128;
+
+        votes_for > 0
+            && votes_cast * 10_000
+                >= self.total_weight as u128 * vault.governance_quorum_bps as u128
+            && votes_for * 10_000 >= votes_cast * vault.governance_threshold_bps as u128
+    }
+}
+impl Vote {
+    pub fn seed<'s>() -> &'s [u8] {
+        b"vote"
+    }
+}
+impl WaitlistEntry {
+    pub fn seed<'s>() -> &'s [u8] {
+        b"waitlist"
+    }
+}
+impl WithdrawRequest {
+    pub fn seed<'s>() -> &'s [u8] {
+        b"withdraw_request"
+    }
+}
+impl Tranche {
+    pub fn seed<'s>() -> &'s [u8] {
+        b"tranche"
+    }
+
+    pub fn calculate_payout(base_amount: &u64, vault: &Vault, tranche: &Tranche) -> u64 {
+        let duration_seconds = vault.end_date - vault.start_date;
+
+        base_amount + calculate_yield(base_amount, tranche.yield_bps, duration_seconds)
+    }
+
+    pub fn outstanding_owed(&self, vault: &Vault) -> u64 {
+        calculate_owed(
+            vault,
+            self.yield_bps,
+            self.amount_collected - self.amount_early_withdrawn,
+            self.amount_redeemed - (self.amount_early_withdrawn - self.penalty_collected),
+        )
     }
 }
 impl User {
     pub fn seed<'s>() -> &'s [u8] {
         b"user"
     }
+
+    pub fn is_vote_locked(&self, now: u64) -> bool {
+        now < self.vote_locked_until
+    }
 }
 
 #[macro_export]
@@ -87,3 +1094,379 @@ macro_rules! assign_if_some {
         }
     };
 }
+
+#[macro_export]
+macro_rules! require_unchanged {
+    ($option:expr, $field:ident, $target:expr) => {
+        if let Some(x) = $option {
+            if $target.$field != x {
+                return err!(ErrorCode::NotUpdatable);
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Yield accrues per 31_536_000 units of the vault dates
+    const YEAR: u64 = 31_536_000;
+
+    fn zeroed<T: AccountDeserialize>(size: usize) -> T {
+        let data = vec![0u8; 8 + size];
+        T::try_deserialize_unchecked(&mut &data[..]).unwrap()
+    }
+
+    fn vault() -> Vault {
+        zeroed(std::mem::size_of::<Vault>())
+    }
+
+    fn amortizing_vault(amortization: AmortizationStyle, yield_bps: u16, count: u64) -> Vault {
+        let mut vault = vault();
+        vault.amortization = amortization;
+        vault.yield_bps = yield_bps;
+        vault.start_date = YEAR;
+        vault.end_date = 2 * YEAR;
+        vault.instalment_count = count;
+        vault
+    }
+
+    fn tranche(index: u8, yield_bps: u16, amount: u64) -> Tranche {
+        let mut tranche: Tranche = zeroed(std::mem::size_of::<Tranche>());
+        tranche.index = index;
+        tranche.yield_bps = yield_bps;
+        tranche.vault_capacity = amount;
+        tranche.amount_collected = amount;
+        tranche
+    }
+
+    fn settle(assets: u64) -> (Vault, Vec<Tranche>) {
+        let mut vault = vault();
+        vault.start_date = YEAR;
+        vault.end_date = 2 * YEAR;
+        vault.amount_collected = 1_000;
+        vault.tranche_count = 2;
+        // Senior owes 630, junior owes 460
+        let mut tranches = vec![tranche(0, 500, 600), tranche(1, 1_500, 400)];
+        vault.settle(assets, tranches.iter_mut(), 2 * YEAR);
+        (vault, tranches)
+    }
+
+    #[test]
+    fn settle_pays_every_tranche_in_full() {
+        let (vault, tranches) = settle(2_000);
+
+        assert!(vault.status == VaultStatus::Settled);
+        assert_eq!(
+            (vault.settled_owed, vault.settled_recovered),
+            (1_090, 1_090)
+        );
+        assert_eq!(
+            (tranches[0].settled_owed, tranches[0].settled_recovered),
+            (630, 630)
+        );
+        assert_eq!(
+            (tranches[1].settled_owed, tranches[1].settled_recovered),
+            (460, 460)
+        );
+    }
+
+    #[test]
+    fn settle_junior_tranche_absorbs_losses_first() {
+        let (vault, tranches) = settle(800);
+
+        assert!(vault.status == VaultStatus::Defaulted);
+        assert_eq!((vault.settled_owed, vault.settled_recovered), (1_090, 800));
+        assert_eq!(tranches[0].settled_recovered, 630);
+        assert_eq!(tranches[1].settled_recovered, 170);
+    }
+
+    #[test]
+    fn settle_senior_tranche_absorbs_losses_once_junior_is_wiped_out() {
+        let (vault, tranches) = settle(500);
+
+        assert!(vault.status == VaultStatus::Defaulted);
+        assert_eq!(vault.settled_recovered, 500);
+        assert_eq!(tranches[0].settled_recovered, 500);
+        assert_eq!(tranches[1].settled_recovered, 0);
+    }
+
+    fn leaf(owner: &Pubkey, cap: u64) -> [u8; 32] {
+        keccak::hashv(&[owner.as_ref(), &cap.to_le_bytes()]).0
+    }
+
+    fn parent(a: [u8; 32], b: [u8; 32]) -> [u8; 32] {
+        match a <= b {
+            true => keccak::hashv(&[&a, &b]).0,
+            false => keccak::hashv(&[&b, &a]).0,
+        }
+    }
+
+    // Allowlist of three wallets: root = parent(parent(a, b), c)
+    fn allowlist() -> (Vault, [Pubkey; 3], [[u8; 32]; 3]) {
+        let owners = [
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        ];
+        let leaves = [
+            leaf(&owners[0], 100),
+            leaf(&owners[1], 200),
+            leaf(&owners[2], 0),
+        ];
+        let mut vault = vault();
+        vault.allowlist_root = parent(parent(leaves[0], leaves[1]), leaves[2]);
+        (vault, owners, leaves)
+    }
+
+    #[test]
+    fn allowlist_accepts_proofs_from_either_side_of_a_pair() {
+        let (vault, owners, leaves) = allowlist();
+        let ab = parent(leaves[0], leaves[1]);
+
+        assert!(Vault::is_allowlisted(
+            &vault,
+            &owners[0],
+            100,
+            &[leaves[1], leaves[2]]
+        ));
+        assert!(Vault::is_allowlisted(
+            &vault,
+            &owners[1],
+            200,
+            &[leaves[0], leaves[2]]
+        ));
+        assert!(Vault::is_allowlisted(&vault, &owners[2], 0, &[ab]));
+    }
+
+    #[test]
+    fn allowlist_rejects_wrong_cap_owner_or_sibling_order() {
+        let (vault, owners, leaves) = allowlist();
+
+        assert!(!Vault::is_allowlisted(
+            &vault,
+            &owners[0],
+            200,
+            &[leaves[1], leaves[2]]
+        ));
+        assert!(!Vault::is_allowlisted(
+            &vault,
+            &Pubkey::new_unique(),
+            100,
+            &[leaves[1], leaves[2]]
+        ));
+        assert!(!Vault::is_allowlisted(
+            &vault,
+            &owners[0],
+            100,
+            &[leaves[2], leaves[1]]
+        ));
+        assert!(!Vault::is_allowlisted(
+            &vault,
+            &owners[0],
+            100,
+            &[leaves[1]]
+        ));
+    }
+
+    fn user(amount: u64) -> User {
+        let mut user: User = zeroed(std::mem::size_of::<User>());
+        user.amount = amount;
+        user
+    }
+
+    fn oversubscribed_vault(capacity: u64, commitments: &[u64]) -> Vault {
+        let mut vault = vault();
+        vault.oversubscription = true;
+        vault.vault_capacity = capacity;
+        vault.amount_committed = commitments.iter().sum();
+        vault.amount_collected = vault.amount_committed;
+        vault
+    }
+
+    #[test]
+    fn allocation_keeps_full_commitments_within_capacity() {
+        let commitments = [300, 200, 500];
+        let vault = oversubscribed_vault(1_000, &commitments);
+
+        for amount in commitments {
+            assert_eq!(Vault::calculate_allocation(&user(amount), &vault), amount);
+        }
+    }
+
+    #[test]
+    fn allocations_never_exceed_capacity() {
+        let commitments = [333, 1, 777, 4_999, 12, 1_000_001, 3];
+        for capacity in [1, 7, 1_000, 99_999, 1_006_000] {
+            let mut vault = oversubscribed_vault(capacity, &commitments);
+            vault.user_count = commitments.len() as u64;
+
+            let allocated: u64 = commitments
+                .iter()
+                .map(|amount| Vault::calculate_allocation(&user(*amount), &vault))
+                .sum();
+            assert!(allocated <= capacity);
+
+            // Rounding leaves at most one unit per user unallocated
+            assert!(capacity - allocated < commitments.len() as u64);
+            assert!(
+                Vault::pending_excess(&vault) >= vault.amount_collected - allocated,
+                "pending excess covers every refund"
+            );
+        }
+    }
+
+    #[test]
+    fn equal_principal_final_instalment_takes_the_rounding() {
+        let vault = amortizing_vault(AmortizationStyle::EqualPrincipal, 0, 3);
+
+        assert_eq!(Vault::calculate_instalments(1_000, &vault, 0, 1), Some(333));
+        assert_eq!(Vault::calculate_instalments(1_000, &vault, 1, 2), Some(333));
+        assert_eq!(Vault::calculate_instalments(1_000, &vault, 2, 3), Some(334));
+        assert_eq!(
+            Vault::calculate_instalments(1_000, &vault, 0, 3),
+            Some(1_000)
+        );
+    }
+
+    #[test]
+    fn instalment_ranges_add_up_to_the_schedule() {
+        for amortization in [
+            AmortizationStyle::EqualPrincipal,
+            AmortizationStyle::Annuity,
+        ] {
+            let vault = amortizing_vault(amortization, 1_200, 12);
+            let total = Vault::calculate_instalments(1_000_000, &vault, 0, 12).unwrap();
+
+            let mut sum = 0;
+            for instalment in 0..12 {
+                sum += Vault::calculate_instalments(1_000_000, &vault, instalment, instalment + 1)
+                    .unwrap();
+            }
+            assert_eq!(sum, total);
+            assert!(total > 1_000_000);
+        }
+    }
+
+    #[test]
+    fn instalments_stop_at_the_final_instalment() {
+        let vault = amortizing_vault(AmortizationStyle::Annuity, 1_200, 12);
+
+        assert_eq!(
+            Vault::calculate_instalments(1_000_000, &vault, 0, 12),
+            Vault::calculate_instalments(1_000_000, &vault, 0, 20)
+        );
+        assert_eq!(
+            Vault::calculate_instalments(1_000_000, &vault, 12, 20),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn annuity_payments_are_level() {
+        let vault = amortizing_vault(AmortizationStyle::Annuity, 1_200, 12);
+        let first = Vault::calculate_instalments(1_000_000, &vault, 0, 1).unwrap();
+
+        for instalment in 1..11 {
+            let payment =
+                Vault::calculate_instalments(1_000_000, &vault, instalment, instalment + 1)
+                    .unwrap();
+            assert!(payment.abs_diff(first) <= 1);
+        }
+    }
+
+    #[test]
+    fn mul_div_rounds_in_the_requested_direction() {
+        assert_eq!(mul_div(10, 3, 4, Rounding::Down), Some(7));
+        assert_eq!(mul_div(10, 3, 4, Rounding::Up), Some(8));
+        // Exact results are not rounded up
+        assert_eq!(mul_div(12, 3, 4, Rounding::Up), Some(9));
+        assert_eq!(mul_div(0, 3, 4, Rounding::Up), Some(0));
+    }
+
+    #[test]
+    fn mul_div_rejects_results_that_overflow() {
+        assert_eq!(mul_div(u64::MAX, 2, 1, Rounding::Down), None);
+        assert_eq!(mul_div(u64::MAX, 1, 1, Rounding::Up), Some(u64::MAX));
+        assert_eq!(mul_div(u64::MAX, u128::MAX, 2, Rounding::Down), None);
+    }
+
+    #[test]
+    fn share_conversions_round_in_the_vaults_favour() {
+        // 1_000 assets backing 3_000 shares
+        let (total_assets, total_shares) = (1_000, 3_000);
+
+        // Deposit: 10 assets are worth 29.98 shares
+        let deposit =
+            Vault::convert_to_shares(10, total_assets, total_shares, Rounding::Down).unwrap();
+        assert_eq!(deposit, 29);
+        // Mint: 29 shares cost 9.67 assets
+        let mint = Vault::convert_to_assets(29, total_assets, total_shares, Rounding::Up).unwrap();
+        assert_eq!(mint, 10);
+        // Withdraw: 10 assets burn 29.98 shares
+        let withdraw =
+            Vault::convert_to_shares(10, total_assets, total_shares, Rounding::Up).unwrap();
+        assert_eq!(withdraw, 30);
+        // Redeem: 29 shares pay 9.67 assets
+        let redeem =
+            Vault::convert_to_assets(29, total_assets, total_shares, Rounding::Down).unwrap();
+        assert_eq!(redeem, 9);
+    }
+
+    #[test]
+    fn share_round_trips_never_profit_the_user() {
+        for (total_assets, total_shares) in [(0, 0), (1_000, 3_000), (3_000, 1_000), (7, 1_000_003)]
+        {
+            for amount in [1, 2, 3, 10, 999, 1_000_000] {
+                // Deposit then redeem returns no more than was deposited
+                let shares =
+                    Vault::convert_to_shares(amount, total_assets, total_shares, Rounding::Down)
+                        .unwrap();
+                let assets =
+                    Vault::convert_to_assets(shares, total_assets, total_shares, Rounding::Down)
+                        .unwrap();
+                assert!(assets <= amount);
+
+                // Mint then withdraw burns at least the shares that were minted
+                let assets =
+                    Vault::convert_to_assets(amount, total_assets, total_shares, Rounding::Up)
+                        .unwrap();
+                let shares =
+                    Vault::convert_to_shares(assets, total_assets, total_shares, Rounding::Up)
+                        .unwrap();
+                assert!(shares >= amount);
+            }
+        }
+    }
+
+    #[test]
+    fn empty_vault_converts_one_to_one() {
+        for rounding in [Rounding::Down, Rounding::Up] {
+            assert_eq!(Vault::convert_to_shares(100, 0, 0, rounding), Some(100));
+            assert_eq!(Vault::convert_to_assets(100, 0, 0, rounding), Some(100));
+        }
+    }
+
+    #[test]
+    fn donation_to_an_empty_vault_does_not_inflate_the_first_deposit() {
+        // 1_000 assets donated before any share exists
+        assert_eq!(
+            Vault::convert_to_shares(100, 1_000, 0, Rounding::Down),
+            Some(0)
+        );
+        assert_eq!(
+            Vault::convert_to_shares(100, 1_000, 0, Rounding::Up),
+            Some(1)
+        );
+        // The virtual share keeps the donation from being redeemed by the first shares
+        assert_eq!(
+            Vault::convert_to_assets(1, 1_000, 0, Rounding::Up),
+            Some(1_001)
+        );
+        assert_eq!(
+            Vault::convert_to_assets(1, 1_000, 1, Rounding::Down),
+            Some(500)
+        );
+    }
+}
@@ -178,7 +178,10 @@ describe("elemental_vault", () => {
   //   await program.methods
   //     .initOrDepositUser(
   //       selectedVault.vaultCount,
-  //       new anchor.BN(USER_DEPOSIT_AMOUNT)
+  //       new anchor.BN(USER_DEPOSIT_AMOUNT),
+  //       0,
+  //       null,
+  //       false
   //     )
   //     .accounts({
   //       owner: user.publicKey,
@@ -186,6 +189,9 @@ describe("elemental_vault", () => {
   //       destinationAta: accounts.vaultAta,
   //       vault: accounts.vault,
   //       user: userPda,
+  //       tranche: null,
+  //       collateralOracle: null,
+  //       credential: null,
   //       baseMint: accounts.baseMint,
   //     })
   //     .signers([user])
@@ -226,7 +232,10 @@ describe("elemental_vault", () => {
   //   await program.methods
   //     .initOrDepositUser(
   //       selectedVault.account.vaultCount,
-  //       new anchor.BN(USER_DEPOSIT_AMOUNT)
+  //       new anchor.BN(USER_DEPOSIT_AMOUNT),
+  //       0,
+  //       null,
+  //       false
   //     )
   //     .accounts({
   //       owner: accounts.user.publicKey,
@@ -234,6 +243,9 @@ describe("elemental_vault", () => {
   //       destinationAta: accounts.vaultAta,
   //       vault: selectedVault.publicKey,
   //       user: userPda,
+  //       tranche: null,
+  //       collateralOracle: null,
+  //       credential: null,
   //       baseMint: accounts.baseMint,
   //     })
   //     .signers([user])
@@ -327,6 +339,8 @@ describe("elemental_vault", () => {
   //         destinationAta: accounts.userMintAta,
   //         vault: accounts.vault,
   //         user: user,
+  //         tranche: null,
+  //         firstLossAta: null,
   //         baseMint: accounts.baseMint,
   //       })
   //       .signers([accounts.user])
@@ -379,6 +393,7 @@ describe("elemental_vault", () => {
           vault: selectedVault.publicKey,
           baseMint: accounts.baseMint,
           creator: creator.publicKey,
+          shareMint: null,
         })
         .signers([authority])
         .rpc();
@@ -1,6 +1,11 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
-import { getGlobalPda, getVaultData, getVaultPda } from "./pda";
+import {
+  getGlobalPda,
+  getProgramDataPda,
+  getVaultData,
+  getVaultPda,
+} from "./pda";
 import { ElementalVault } from "../target/types/elemental_vault";
 import { IAccounts } from "./types";
 import { assert, expect } from "chai";
@@ -107,10 +112,11 @@ export const initGlobal = async (
     await program.methods
       .initGlobal()
       .accounts({
-        initializer: accounts.creator.publicKey,
+        initializer: program.provider.publicKey,
         global: accounts.global,
+        program: program.programId,
+        programData: getProgramDataPda(program),
       })
-      .signers([accounts.creator])
       .rpc();
   } catch (error) {
     console.log("error", error);
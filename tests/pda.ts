@@ -10,6 +10,14 @@ export const getGlobalPda = (program: Program<ElementalVault>) => {
   );
   return globalPda;
 };
+export const getProgramDataPda = (program: Program<ElementalVault>) => {
+  const [programDataPda, _programDataPdaBump] =
+    PublicKey.findProgramAddressSync(
+      [program.programId.toBuffer()],
+      new PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
+    );
+  return programDataPda;
+};
 export const getVaultPda = (
   program: Program<ElementalVault>,
   vaultCount: anchor.BN